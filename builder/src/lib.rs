@@ -2,6 +2,7 @@ use proc_macro::TokenStream;
 use quote::{format_ident, quote, ToTokens};
 use std::fs;
 use std::process::Command;
+use syn::punctuated::Punctuated;
 use syn::*;
 
 /// Represents types with one generic argument, e.g. Option<T> or Vec<T>
@@ -10,13 +11,13 @@ struct Type1<'a> {
     t0: &'a GenericArgument,
 }
 
-/// Returns a Type1 struct, but only when the type is an Option
-fn get_option(t: &Type) -> Option<Type1> {
+/// Returns a Type1 struct, but only when the type is the given wrapper, e.g. `Option`
+fn get_type1<'a>(t: &'a Type, wrapper: &str) -> Option<Type1<'a>> {
     match t {
         Type::Path(TypePath { qself: None, path }) => {
             let ps = path.segments.first()?;
 
-            if "Option" == ps.ident.to_string() {
+            if ps.ident == wrapper {
                 let generic_argument = match &ps.arguments {
                     PathArguments::AngleBracketed(AngleBracketedGenericArguments {
                         args, ..
@@ -38,11 +39,359 @@ fn get_option(t: &Type) -> Option<Type1> {
     }
 }
 
+/// Returns a Type1 struct, but only when the type is an Option
+fn get_option(t: &Type) -> Option<Type1<'_>> {
+    get_type1(t, "Option")
+}
+
+/// Struct-level options, e.g. #[builder(from_env)]
+#[derive(Default)]
+struct BuilderOptions {
+    from_env: bool,
+    cli: bool,
+    patch: bool,
+    shared: bool,
+    async_build: bool,
+    describe: bool,
+}
+
+impl BuilderOptions {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = BuilderOptions::default();
+        for meta in builder_metas(attrs)? {
+            match &meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("from_env") => {
+                    options.from_env = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("cli") => {
+                    options.cli = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("patch") => {
+                    options.patch = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("shared") => {
+                    options.shared = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("async_build") => {
+                    options.async_build = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("describe") => {
+                    options.describe = true;
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected one of `from_env`, `cli`, `patch`, `shared`, `async_build`, \
+                         `describe`",
+                    ))
+                }
+            }
+        }
+        if options.cli && options.async_build {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "`builder(cli)` cannot be combined with `builder(async_build)`",
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// A field of the input struct, together with its #[builder(...)] attributes
+struct BuilderField<'a> {
+    name: &'a Ident,
+    ty: &'a Type,
+    /// Set when the field is an Option<T>
+    option: Option<Type1<'a>>,
+    /// Set by #[builder(each = "...")]: the one-at-a-time setter and the Vec<T>
+    each: Option<(Ident, Type1<'a>)>,
+    /// The field's doc comment, joined into a single line
+    doc: String,
+    /// Set by #[builder(default_with = "...")]: a closure from &Builder to the builder's Option
+    default_with: Option<Expr>,
+    /// Set by #[builder(depends_on = "a, b")]: fields whose defaults must be computed first
+    depends_on: Option<(LitStr, Vec<Ident>)>,
+    /// Set by #[builder(setter(custom))]: the user writes the setter
+    custom_setter: bool,
+    /// Set by #[builder(validate = "...")]: a function from &T to Result<(), E>, and whether it
+    /// is async, from #[builder(validate_async = "...")]
+    validate: Option<(ExprPath, bool)>,
+    /// Set by #[builder(alias = "...")]: extra setters forwarding to the field's setter
+    aliases: Vec<Ident>,
+    /// Set by #[builder(deprecated = "...")]: the deprecation note of the aliases
+    deprecated: Option<LitStr>,
+}
+
+impl<'a> BuilderField<'a> {
+    fn parse(field: &'a Field) -> Result<Self> {
+        let name = field.ident.as_ref().unwrap();
+        let mut each = None;
+        let mut default_with = None;
+        let mut depends_on = None;
+        let mut custom_setter = false;
+        let mut validate = None;
+        let mut aliases = Vec::new();
+        let mut deprecated = None;
+        for meta in builder_metas(&field.attrs)? {
+            match &meta {
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("each") => {
+                    let vec = get_type1(&field.ty, "Vec").ok_or_else(|| {
+                        Error::new_spanned(&field.ty, "`each` requires a field of type Vec<T>")
+                    })?;
+                    each = Some((lit.parse()?, vec));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("default_with") => {
+                    default_with = Some(lit.parse()?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("depends_on") => {
+                    let names = lit.parse_with(Punctuated::<Ident, Token![,]>::parse_terminated)?;
+                    depends_on = Some((lit.clone(), names.into_iter().collect()));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("validate") => {
+                    validate = Some((lit.parse()?, false));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("validate_async") => {
+                    validate = Some((lit.parse()?, true));
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("alias") => {
+                    aliases.push(lit.parse()?);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("deprecated") => {
+                    deprecated = Some(lit.clone());
+                }
+                NestedMeta::Meta(Meta::List(MetaList { path, nested, .. }))
+                    if path.is_ident("setter") =>
+                {
+                    for meta in nested {
+                        match meta {
+                            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("custom") => {
+                                custom_setter = true;
+                            }
+                            _ => return Err(Error::new_spanned(meta, "expected `setter(custom)`")),
+                        }
+                    }
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected one of `each`, `default_with`, `depends_on`, `setter(custom)`, \
+                         `validate`, `validate_async`, `alias`, `deprecated`",
+                    ))
+                }
+            }
+        }
+        if let (Some(_), Some(expr)) = (&each, &default_with) {
+            return Err(Error::new_spanned(
+                expr,
+                "`default_with` is not supported on `each` fields",
+            ));
+        }
+        if let (None, Some((lit, _))) = (&default_with, &depends_on) {
+            return Err(Error::new_spanned(
+                lit,
+                "`depends_on` requires `default_with`",
+            ));
+        }
+        if let (true, Some(alias)) = (custom_setter, aliases.first()) {
+            return Err(Error::new_spanned(
+                alias,
+                "`alias` requires a generated setter",
+            ));
+        }
+        if let (true, Some(lit)) = (aliases.is_empty(), &deprecated) {
+            return Err(Error::new_spanned(lit, "`deprecated` requires `alias`"));
+        }
+        Ok(BuilderField {
+            name,
+            ty: &field.ty,
+            option: get_option(&field.ty),
+            each,
+            doc: doc_string(&field.attrs),
+            default_with,
+            depends_on,
+            custom_setter,
+            validate,
+            aliases,
+            deprecated,
+        })
+    }
+
+    /// The field type if it is already an Option<T>, otherwise Option<#ty>
+    fn optional_ty(&self) -> proc_macro2::TokenStream {
+        match &self.option {
+            Some(Type1 { ty, .. }) => ty.to_token_stream(),
+            None => {
+                let ty = self.ty;
+                quote!(Option<#ty>)
+            }
+        }
+    }
+
+    /// The type accepted by the all-at-once setter
+    fn setter_ty(&self) -> proc_macro2::TokenStream {
+        match &self.option {
+            Some(Type1 { t0, .. }) => t0.to_token_stream(),
+            None => self.ty.to_token_stream(),
+        }
+    }
+
+    /// The generated setters as (method name, argument type)
+    fn setters(&self) -> Vec<(&Ident, proc_macro2::TokenStream)> {
+        if self.custom_setter {
+            return Vec::new();
+        }
+        match &self.each {
+            Some((each, Type1 { t0, .. })) if each == self.name => {
+                vec![(each, t0.to_token_stream())]
+            }
+            Some((each, Type1 { t0, .. })) => {
+                vec![(self.name, self.setter_ty()), (each, t0.to_token_stream())]
+            }
+            None => vec![(self.name, self.setter_ty())],
+        }
+    }
+
+    /// The alias setters as (alias, canonical setter, argument type). Aliases forward to the
+    /// field's all-at-once setter, or to its `each` setter if that has the field's name.
+    fn alias_setters(&self) -> Vec<(&Ident, &Ident, proc_macro2::TokenStream)> {
+        match self.setters().into_iter().next() {
+            Some((setter, ty)) => self
+                .aliases
+                .iter()
+                .map(|alias| (alias, setter, ty.clone()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// `#[deprecated]` for the alias setters, if requested
+    fn deprecation(&self) -> proc_macro2::TokenStream {
+        match &self.deprecated {
+            Some(note) => quote!(#[deprecated(note = #note)]),
+            None => quote!(),
+        }
+    }
+
+    /// The type parsed from a string by `set_by_name`, i.e. one element for `each` fields
+    fn parsed_ty(&self) -> proc_macro2::TokenStream {
+        match &self.each {
+            Some((_, Type1 { t0, .. })) => t0.to_token_stream(),
+            None => self.setter_ty(),
+        }
+    }
+}
+
+/// Orders the fields with a `default_with` so that every field comes after the fields it
+/// `depends_on`, keeping declaration order otherwise
+fn default_order<'f, 'a>(fields: &'f [BuilderField<'a>]) -> Result<Vec<&'f BuilderField<'a>>> {
+    /// Depth-first visit; `visiting` holds the fields on the current path, to detect cycles
+    fn visit<'f, 'a>(
+        field: &'f BuilderField<'a>,
+        fields: &'f [BuilderField<'a>],
+        visiting: &mut Vec<&'f Ident>,
+        order: &mut Vec<&'f BuilderField<'a>>,
+    ) -> Result<()> {
+        if order.iter().any(|f| f.name == field.name) || field.default_with.is_none() {
+            return Ok(());
+        }
+        if visiting.contains(&field.name) {
+            let (lit, _) = field.depends_on.as_ref().unwrap();
+            return Err(Error::new_spanned(
+                lit,
+                format!("cycle in `depends_on` involving `{}`", field.name),
+            ));
+        }
+        visiting.push(field.name);
+        if let Some((_, names)) = &field.depends_on {
+            for name in names {
+                let dependency = fields.iter().find(|f| f.name == name).ok_or_else(|| {
+                    Error::new_spanned(name, format!("no field named `{}`", name))
+                })?;
+                visit(dependency, fields, visiting, order)?;
+            }
+        }
+        visiting.pop();
+        order.push(field);
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    for field in fields {
+        visit(field, fields, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order)
+}
+
+/// Joins the lines of a doc comment into a single line
+fn doc_string(attrs: &[Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match attr.parse_meta() {
+        Ok(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(lit),
+            ..
+        })) if path.is_ident("doc") => Some(lit.value().trim().to_owned()),
+        _ => None,
+    });
+    lines
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Collects the nested metas of all #[builder(...)] attributes
+fn builder_metas(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("builder")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => return Err(Error::new_spanned(meta, "expected `builder(...)`")),
+        }
+    }
+    Ok(metas)
+}
+
 #[proc_macro_derive(Builder, attributes(builder, milder, foobar))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    let ts = expand(input).unwrap_or_else(Error::into_compile_error);
+
+    let ts = proc_macro::TokenStream::from(ts);
+    save_and_format(&ts, "builder.rs");
+    ts
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
     let struct_name = input.ident;
     let builder_name = format_ident!("{}Builder", struct_name);
+    let options = BuilderOptions::parse(&input.attrs)?;
 
     let fields = match input.data {
         Data::Struct(data) => match data.fields {
@@ -51,28 +400,39 @@ pub fn derive(input: TokenStream) -> TokenStream {
         },
         _ => panic!(),
     };
+    let fields = fields
+        .named
+        .iter()
+        .map(BuilderField::parse)
+        .collect::<Result<Vec<_>>>()?;
 
     // pub struct CommandBuilder {
     //     executable: Option<String>,
-    //     args: Option<Vec<String>>,
+    //     args: Vec<String>,
     //     env: Option<Vec<String>>,
     //     current_dir: Option<String>,
     // }
     let command_builder = {
-        let recurse = fields.named.iter().map(|f| {
-            let name = &f.ident;
-            if let Some(Type1 { ty, .. }) = get_option(&f.ty) {
+        let recurse = fields.iter().map(|f| {
+            let name = f.name;
+            if let Some((_, Type1 { ty, .. })) = &f.each {
                 quote! {
                     #name: #ty
                 }
             } else {
-                let ty = &f.ty;
+                let ty = f.optional_ty();
                 quote! {
-                    #name: Option<#ty>
+                    #name: #ty
                 }
             }
         });
+        let derive_clone = if options.shared {
+            quote!(#[derive(Clone)])
+        } else {
+            quote!()
+        };
         quote! {
+            #derive_clone
             pub struct #builder_name {
                 #(#recurse), *
             }
@@ -84,54 +444,206 @@ pub fn derive(input: TokenStream) -> TokenStream {
     //         self.executable = Some(executable);
     //         self
     //     }
-    //     ...
+    //     fn arg(&mut self, arg: String) -> &mut Self {
+    //         self.args.push(arg);
+    //         self
+    //     }
+    //     ... (no setter for fields with #[builder(setter(custom))])
+    //     #[deprecated(note = "use executable")] // #[builder(alias = "exe", deprecated = "...")]
+    //     fn exe(&mut self, exe: String) -> &mut Self {
+    //         self.executable(exe)
+    //     }
     //     pub fn build(&mut self) -> Result<Command, Box<dyn std::error::Error>> {
-    //         Ok(Command {
-    //             executable: self.executable.clone().unwrap(),
-    //             args: self.args.clone().unwrap(),
-    //             env: self.env.clone().unwrap(),
-    //             current_dir: self.current_dir.clone(),
-    //         })
+    //         // only with #[builder(default_with = "...")] fields: the defaults are computed on
+    //         // a copy, so that they are computed again by the next build
+    //         let mut builder = CommandBuilder {
+    //             executable: self.executable.clone(),
+    //             ...
+    //         };
+    //         // for each #[builder(default_with = "...")], dependencies first
+    //         if builder.current_dir.is_none() {
+    //             builder.current_dir = (|b: &Self| ...)(&builder);
+    //         }
+    //         let built = Command {
+    //             executable: builder.executable.ok_or("missing field `executable`")?,
+    //             args: builder.args,
+    //             env: builder.env.ok_or("missing field `env`")?,
+    //             current_dir: builder.current_dir,
+    //         };
+    //
+    //         // without defaults, the fields are cloned from self instead:
+    //         let built = Command {
+    //             executable: self.executable.clone().ok_or("missing field `executable`")?,
+    //             ...
+    //         };
+    //         // for each #[builder(validate = "...")]
+    //         validate_dir(&built.current_dir)?;
+    //         Ok(built)
     //     }
     // }
+    //
+    // With #[builder(async_build)], `build` consumes the builder instead:
+    //
+    //     pub async fn build(mut self) -> Result<Command, Box<dyn std::error::Error>> {
+    //         if self.current_dir.is_none() {
+    //             self.current_dir = (async |b: &Self| ...)(&self).await; // only async closures
+    //         }
+    //         let built = Command {
+    //             executable: self.executable.ok_or("missing field `executable`")?,
+    //             ...
+    //         };
+    //         validate_dir(&built.current_dir).await?; // #[builder(validate_async = "...")]
+    //         Ok(built)
+    //     }
     let command_builder_impl = {
-        let setters = fields.named.iter().map(|f| {
-            let name = &f.ident;
+        let alias_setters = fields.iter().flat_map(|f| {
+            let deprecation = f.deprecation();
+            f.alias_setters()
+                .into_iter()
+                .map(move |(alias, setter, ty)| {
+                    quote! {
+                        #deprecation
+                        fn #alias(&mut self, #alias: #ty) -> &mut Self {
+                            self.#setter(#alias)
+                        }
+                    }
+                })
+        });
+        let setters = fields.iter().filter(|f| !f.custom_setter).map(|f| {
+            let name = f.name;
+            let ty = f.setter_ty();
 
-            let ty = if let Some(Type1 { ty: _, t0 }) = get_option(&f.ty) {
-                t0.to_token_stream()
-            } else {
-                f.ty.to_token_stream()
-            };
-            quote! {
+            let all_at_once = quote! {
                 fn #name(&mut self, #name: #ty) -> &mut Self {
                     self.#name = Some(#name);
                     self
                 }
+            };
+            match &f.each {
+                Some((each, Type1 { t0, .. })) => {
+                    let one_at_a_time = quote! {
+                        fn #each(&mut self, #each: #t0) -> &mut Self {
+                            self.#name.push(#each);
+                            self
+                        }
+                    };
+                    if each == name {
+                        one_at_a_time
+                    } else {
+                        quote! {
+                            fn #name(&mut self, #name: #ty) -> &mut Self {
+                                self.#name = #name;
+                                self
+                            }
+                            #one_at_a_time
+                        }
+                    }
+                }
+                None => all_at_once,
             }
         });
-        let field_constructors = fields.named.iter().map(|f| {
-            let name = &f.ident;
+        let defaulted = default_order(&fields)?;
+        // Where the defaults are computed and the fields are moved out of: the consumed builder
+        // of an async build, or a copy of the fields. None when they are cloned from self.
+        let (source, copy) = if options.async_build {
+            (Some(quote!(self)), quote!())
+        } else if defaulted.is_empty() {
+            (None, quote!())
+        } else {
+            let names = fields.iter().map(|f| f.name);
+            (
+                Some(quote!(builder)),
+                quote! {
+                    let mut builder = #builder_name {
+                        #(#names: self.#names.clone()),*
+                    };
+                },
+            )
+        };
+        let defaults = defaulted
+            .into_iter()
+            .map(|f| {
+                let name = f.name;
+                let default_with = f.default_with.as_ref().unwrap();
+                let is_async = matches!(
+                    default_with,
+                    Expr::Closure(ExprClosure {
+                        asyncness: Some(_),
+                        ..
+                    })
+                );
+                Ok(match (is_async, options.async_build) {
+                    (true, true) => quote! {
+                        if #source.#name.is_none() {
+                            #source.#name = (#default_with)(&#source).await;
+                        }
+                    },
+                    (false, _) => quote! {
+                        if #source.#name.is_none() {
+                            #source.#name = (#default_with)(&#source);
+                        }
+                    },
+                    (true, false) => {
+                        return Err(Error::new_spanned(
+                            default_with,
+                            "an async `default_with` requires `builder(async_build)`",
+                        ))
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let field_constructors = fields.iter().map(|f| {
+            let name = f.name;
+            let value = match &source {
+                Some(source) => quote!(#source.#name),
+                None => quote!(self.#name.to_owned()),
+            };
 
-            if let Some(_) = get_option(&f.ty) {
+            if f.option.is_some() || f.each.is_some() {
                 quote! {
-                    #name: self.#name.to_owned()
+                    #name: #value
                 }
             } else {
+                let missing = format!("missing field `{}`", name);
                 quote! {
-                    #name: self.#name.to_owned().unwrap()
+                    #name: #value.ok_or(#missing)?
                 }
             }
         });
+        let validators = fields
+            .iter()
+            .filter_map(|f| {
+                let name = f.name;
+                let (validate, is_async) = f.validate.as_ref()?;
+                Some(match (is_async, options.async_build) {
+                    (true, true) => Ok(quote!(#validate(&built.#name).await?;)),
+                    (false, _) => Ok(quote!(#validate(&built.#name)?;)),
+                    (true, false) => Err(Error::new_spanned(
+                        validate,
+                        "`validate_async` requires `builder(async_build)`",
+                    )),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let signature = if options.async_build {
+            quote!(pub async fn build(mut self) -> Result<#struct_name, Box<dyn std::error::Error>>)
+        } else {
+            quote!(pub fn build(&mut self) -> Result<#struct_name, Box<dyn std::error::Error>>)
+        };
 
         quote! {
             impl #builder_name {
                 #(#setters)*
+                #(#alias_setters)*
 
-                pub fn build(&mut self) -> Result<#struct_name, Box<dyn std::error::Error>> {
-                    Ok(#struct_name {
+                #signature {
+                    #copy
+                    #(#defaults)*
+                    let built = #struct_name {
                         #(#field_constructors),*
-                    })
+                    };
+                    #(#validators)*
+                    Ok(built)
                 }
             }
         }
@@ -141,34 +653,474 @@ pub fn derive(input: TokenStream) -> TokenStream {
     //     pub fn builder() -> CommandBuilder {
     //         CommandBuilder {
     //             executable: None,
-    //             args: None,
+    //             args: Vec::new(),
     //             env: None,
     //             current_dir: None,
     //         }
     //     }
     // }
     let command_impl = {
-        let fields = fields.named.iter().map(|f| &f.ident);
+        let fields = fields.iter().map(|f| {
+            let name = f.name;
+            if f.each.is_some() {
+                quote!(#name: Vec::new())
+            } else {
+                quote!(#name: None)
+            }
+        });
         quote! {
             impl #struct_name {
                 pub fn builder() -> #builder_name {
                     #builder_name {
-                        #(#fields: None),*
+                        #(#fields),*
+                    }
+                }
+            }
+        }
+    };
+
+    // Only with #[builder(from_env)] or #[builder(cli)]:
+    //
+    // impl CommandBuilder {
+    //     pub fn set_by_name(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    //         match key {
+    //             "executable" => {
+    //                 self.executable = Some(parse("executable", value)?);
+    //             }
+    //             "args" | "arg" => {
+    //                 self.args.push(parse("args", value)?);
+    //             }
+    //             ...
+    //             _ => return Err(format!("unknown field `{}`", key).into()),
+    //         }
+    //         Ok(())
+    //     }
+    //
+    //     // Only with #[builder(from_env)]
+    //     pub fn from_env(prefix: &str) -> Result<Self, Box<dyn Error>> {
+    //         let mut builder = Command::builder();
+    //         if let Some(value) = var(prefix, "EXECUTABLE")? {
+    //             builder.set_by_name("executable", &value)?;
+    //         }
+    //         if let Some(value) = var(prefix, "ARGS")? {
+    //             for value in value.split(',').filter(|v| !v.is_empty()) {
+    //                 builder.set_by_name("args", value)?;
+    //             }
+    //         }
+    //         ...
+    //         Ok(builder)
+    //     }
+    // }
+    let command_builder_set_by_name = if options.from_env || options.cli {
+        let arms = fields.iter().map(|f| {
+            let name = f.name;
+            let key = name.to_string();
+            let ty = f.parsed_ty();
+            let parse = quote! {
+                value.parse::<#ty>().map_err(|e| {
+                    format!("invalid value for `{}`: {}", #key, e)
+                })?
+            };
+            let mut keys = vec![key.clone()];
+            keys.extend(f.aliases.iter().map(Ident::to_string));
+            match &f.each {
+                Some((each, _)) if each != name => {
+                    keys.push(each.to_string());
+                    quote!(#(#keys)|* => self.#name.push(#parse),)
+                }
+                Some(_) => quote!(#(#keys)|* => self.#name.push(#parse),),
+                None => quote!(#(#keys)|* => self.#name = Some(#parse),),
+            }
+        });
+        let vars = fields.iter().map(|f| {
+            let key = f.name.to_string();
+            let var = key.to_uppercase();
+            let set = if f.each.is_some() {
+                quote! {
+                    for value in value.split(',').filter(|v| !v.is_empty()) {
+                        builder.set_by_name(#key, value)?;
+                    }
+                }
+            } else {
+                quote!(builder.set_by_name(#key, &value)?;)
+            };
+            quote! {
+                match std::env::var(format!("{}{}", prefix, #var)) {
+                    Ok(value) => { #set }
+                    Err(std::env::VarError::NotPresent) => {}
+                    Err(e) => return Err(format!("{}{}: {}", prefix, #var, e).into()),
+                }
+            }
+        });
+
+        let from_env = if options.from_env {
+            quote! {
+                /// Creates a builder from the environment variables named `prefix` followed by
+                /// the upper-cased field name. `each` fields take a comma-separated list.
+                pub fn from_env(prefix: &str) -> Result<Self, Box<dyn std::error::Error>> {
+                    let mut builder = #struct_name::builder();
+                    #(#vars)*
+                    Ok(builder)
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        quote! {
+            impl #builder_name {
+                /// Parses `value` with `FromStr` and stores it in the field named `key` (or one of
+                /// its aliases). For `each` fields, both the field name and the `each` name append
+                /// one element.
+                pub fn set_by_name(
+                    &mut self,
+                    key: &str,
+                    value: &str,
+                ) -> Result<(), Box<dyn std::error::Error>> {
+                    match key {
+                        #(#arms)*
+                        _ => return Err(format!("unknown field `{}`", key).into()),
+                    }
+                    Ok(())
+                }
+
+                #from_env
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    // Only with #[builder(cli)]:
+    //
+    // impl CommandBuilder {
+    //     pub fn help() -> &'static str {
+    //         "Options:\n    --executable <EXECUTABLE>  ...\n..."
+    //     }
+    // }
+    //
+    // #[derive(Debug)]
+    // pub struct CommandHelp;
+    //
+    // impl Display for CommandHelp { /* CommandBuilder::help() */ }
+    // impl Error for CommandHelp {}
+    //
+    // impl Command {
+    //     pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, Box<dyn Error>> {
+    //         let mut builder = Command::builder();
+    //         let mut args = args.into_iter().skip(1);
+    //         while let Some(arg) = args.next() {
+    //             // --help returns CommandHelp, --key value and --key=value are forwarded to
+    //             // set_by_name
+    //         }
+    //         builder.build()
+    //     }
+    // }
+    let command_cli = if options.cli {
+        let help = cli_help(&input.attrs, &fields);
+        let help_name = format_ident!("{}Help", struct_name);
+        let help_doc = format!(
+            " `--help` (or `-h`) fails with a `{}` error, which displays as the help text.",
+            help_name
+        );
+
+        quote! {
+            impl #builder_name {
+                /// The `--help` text
+                pub fn help() -> &'static str {
+                    #help
+                }
+            }
+
+            /// The error returned by `from_args` when `--help` is passed. It displays as the
+            /// help text, for the caller to print before exiting.
+            #[derive(Debug)]
+            pub struct #help_name;
+
+            impl std::fmt::Display for #help_name {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str(#builder_name::help())
+                }
+            }
+
+            impl std::error::Error for #help_name {}
+
+            impl #struct_name {
+                /// Parses `--field value` (or `--field=value`) command line arguments. The first
+                /// item is the program name, as with `std::env::args()`.
+                #[doc = #help_doc]
+                pub fn from_args<I: IntoIterator<Item = String>>(
+                    args: I,
+                ) -> Result<#struct_name, Box<dyn std::error::Error>> {
+                    let mut builder = #struct_name::builder();
+                    let mut args = args.into_iter().skip(1);
+                    while let Some(arg) = args.next() {
+                        if arg == "--help" || arg == "-h" {
+                            return Err(Box::new(#help_name));
+                        }
+                        let option = match arg.strip_prefix("--") {
+                            Some(option) => option,
+                            None => return Err(format!("unexpected argument `{}`", arg).into()),
+                        };
+                        let (key, value) = match option.split_once('=') {
+                            Some((key, value)) => (key, value.to_owned()),
+                            None => match args.next() {
+                                Some(value) => (option, value),
+                                None => return Err(format!("`{}` requires a value", arg).into()),
+                            },
+                        };
+                        builder.set_by_name(&key.replace('-', "_"), &value)?;
+                    }
+                    builder.build()
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    // Only with #[builder(patch)]:
+    //
+    // #[derive(Default)]
+    // pub struct CommandPatch {
+    //     pub executable: Option<String>,
+    //     pub args: Option<Vec<String>>,
+    //     pub env: Option<Vec<String>>,
+    //     pub current_dir: Option<Option<String>>,
+    // }
+    //
+    // impl Command {
+    //     pub fn apply(&mut self, patch: CommandPatch) {
+    //         if let Some(executable) = patch.executable {
+    //             self.executable = executable;
+    //         }
+    //         ...
+    //         if let Some(current_dir) = patch.current_dir {
+    //             self.current_dir = current_dir;
+    //         }
+    //     }
+    // }
+    let command_patch = if options.patch {
+        let patch_name = format_ident!("{}Patch", struct_name);
+        let patch_fields = fields.iter().map(|f| {
+            let name = f.name;
+            let ty = f.ty;
+            quote! {
+                pub #name: Option<#ty>
+            }
+        });
+        let updates = fields.iter().map(|f| {
+            let name = f.name;
+            quote! {
+                if let Some(#name) = patch.#name {
+                    self.#name = #name;
+                }
+            }
+        });
+
+        quote! {
+            /// Every field of the original struct, wrapped in an `Option`. Fields that are `None`
+            /// are left untouched by `apply`, so an `Option` field is cleared with `Some(None)`.
+            #[derive(Default)]
+            pub struct #patch_name {
+                #(#patch_fields),*
+            }
+
+            impl #struct_name {
+                pub fn apply(&mut self, patch: #patch_name) {
+                    #(#updates)*
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    // Only with #[builder(shared)]:
+    //
+    // #[derive(Clone)]
+    // pub struct CommandSharedBuilder {
+    //     inner: std::sync::Arc<std::sync::Mutex<CommandBuilder>>,
+    // }
+    //
+    // impl CommandSharedBuilder {
+    //     fn executable(&self, executable: String) -> &Self {
+    //         self.inner.lock().unwrap().executable(executable);
+    //         self
+    //     }
+    //     ...
+    //     pub fn build(&self) -> Result<Command, Box<dyn std::error::Error>> {
+    //         let mut snapshot = self.inner.lock().unwrap().clone();
+    //         snapshot.build()
+    //     }
+    // }
+    let command_shared_builder = if options.shared {
+        let shared_name = format_ident!("{}SharedBuilder", struct_name);
+        let setters = fields.iter().flat_map(|f| f.setters()).map(|(name, ty)| {
+            quote! {
+                fn #name(&self, #name: #ty) -> &Self {
+                    self.inner.lock().unwrap().#name(#name);
+                    self
+                }
+            }
+        });
+        let alias_setters = fields.iter().flat_map(|f| {
+            let deprecation = f.deprecation();
+            f.alias_setters()
+                .into_iter()
+                .map(move |(alias, setter, ty)| {
+                    quote! {
+                        #deprecation
+                        fn #alias(&self, #alias: #ty) -> &Self {
+                            self.#setter(#alias)
+                        }
                     }
+                })
+        });
+        let build = if options.async_build {
+            quote! {
+                /// Builds from a snapshot of the fields set so far; the shared state is unchanged.
+                pub async fn build(&self) -> Result<#struct_name, Box<dyn std::error::Error>> {
+                    let snapshot = self.inner.lock().unwrap().clone();
+                    snapshot.build().await
+                }
+            }
+        } else {
+            quote! {
+                /// Builds from a snapshot of the fields set so far; the shared state is unchanged.
+                pub fn build(&self) -> Result<#struct_name, Box<dyn std::error::Error>> {
+                    let mut snapshot = self.inner.lock().unwrap().clone();
+                    snapshot.build()
+                }
+            }
+        };
+
+        quote! {
+            /// A handle to the builder that can be cloned and shared between threads.
+            #[derive(Clone)]
+            pub struct #shared_name {
+                inner: std::sync::Arc<std::sync::Mutex<#builder_name>>,
+            }
+
+            impl #shared_name {
+                #(#setters)*
+                #(#alias_setters)*
+
+                /// Runs `f` with exclusive access to the builder, e.g. to call custom setters.
+                pub fn with<R>(&self, f: impl FnOnce(&mut #builder_name) -> R) -> R {
+                    f(&mut self.inner.lock().unwrap())
+                }
+
+                #build
+            }
+
+            impl From<#builder_name> for #shared_name {
+                fn from(builder: #builder_name) -> Self {
+                    #shared_name {
+                        inner: std::sync::Arc::new(std::sync::Mutex::new(builder)),
+                    }
+                }
+            }
+
+            impl #struct_name {
+                pub fn shared_builder() -> #shared_name {
+                    #shared_name::from(#struct_name::builder())
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    // Only with #[builder(describe)]:
+    //
+    // impl CommandBuilder {
+    //     pub fn describe(&self) -> Vec<(&'static str, Option<String>)> {
+    //         vec![
+    //             ("executable", self.executable.as_ref().map(|v| format!("{:?}", v))),
+    //             ("args", Some(format!("{:?}", self.args))),
+    //             ...
+    //         ]
+    //     }
+    // }
+    //
+    // impl std::fmt::Display for CommandBuilder {
+    //     // one `key = value` line per field, `<unset>` for missing values
+    // }
+    let command_builder_describe = if options.describe {
+        let entries = fields.iter().map(|f| {
+            let name = f.name;
+            let key = name.to_string();
+            if f.each.is_some() {
+                quote!((#key, Some(format!("{:?}", self.#name))))
+            } else {
+                quote!((#key, self.#name.as_ref().map(|v| format!("{:?}", v))))
+            }
+        });
+
+        quote! {
+            impl #builder_name {
+                /// The fields in declaration order, rendered with `Debug`; `None` if not set yet.
+                pub fn describe(&self) -> Vec<(&'static str, Option<String>)> {
+                    vec![#(#entries),*]
+                }
+            }
+
+            impl std::fmt::Display for #builder_name {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    for (key, value) in self.describe() {
+                        writeln!(f, "{} = {}", key, value.as_deref().unwrap_or("<unset>"))?;
+                    }
+                    Ok(())
                 }
             }
         }
+    } else {
+        quote!()
     };
 
-    let expanded = quote! {
+    Ok(quote! {
         #command_builder
         #command_builder_impl
         #command_impl
-    };
+        #command_builder_set_by_name
+        #command_cli
+        #command_patch
+        #command_shared_builder
+        #command_builder_describe
+    })
+}
 
-    let ts = proc_macro::TokenStream::from(expanded);
-    save_and_format(&ts, "builder.rs");
-    ts
+/// Renders the `--help` text: the struct's doc comment followed by one line per option
+fn cli_help(attrs: &[Attribute], fields: &[BuilderField]) -> String {
+    let options = fields
+        .iter()
+        .map(|f| {
+            let name = f.each.as_ref().map_or(f.name, |e| &e.0).to_string();
+            let mut option = format!("--{} <{}>", name.replace('_', "-"), name.to_uppercase());
+            let mut doc = f.doc.clone();
+            if f.each.is_some() {
+                option.push_str("...");
+                doc.push_str(" (repeatable)");
+            } else if f.option.is_some() {
+                doc.push_str(" (optional)");
+            }
+            (option, doc.trim_start().to_owned())
+        })
+        .chain(Some(("-h, --help".to_owned(), "Print help".to_owned())))
+        .collect::<Vec<_>>();
+    let width = options.iter().map(|(o, _)| o.len()).max().unwrap_or(0);
+
+    let mut help = doc_string(attrs);
+    if !help.is_empty() {
+        help.push_str("\n\n");
+    }
+    help.push_str("Options:\n");
+    for (option, doc) in options {
+        help.push_str(format!("    {:width$}  {}", option, doc, width = width).trim_end());
+        help.push('\n');
+    }
+    help
 }
 
 /// saves the token stream into a file, and tries to reformat it
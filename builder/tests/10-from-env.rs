@@ -0,0 +1,54 @@
+// Services are commonly configured from environment variables and simple
+// `key=value` files. With #[builder(from_env)] the builder gets two more
+// methods:
+//
+//     impl CommandBuilder {
+//         pub fn set_by_name(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>>;
+//         pub fn from_env(prefix: &str) -> Result<CommandBuilder, Box<dyn Error>>;
+//     }
+//
+// Every value is parsed with FromStr. Fields with #[builder(each = "...")]
+// append one element per call to set_by_name, and read a comma-separated list
+// from the environment.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(from_env)]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(each = "alias")]
+    aliases: Vec<String>,
+    workers: Option<usize>,
+}
+
+fn main() {
+    std::env::set_var("MYAPP_HOST", "localhost");
+    std::env::set_var("MYAPP_PORT", "8080");
+    std::env::set_var("MYAPP_ALIASES", "a.example,b.example");
+
+    let server = ServerBuilder::from_env("MYAPP_").unwrap().build().unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.aliases, vec!["a.example", "b.example"]);
+    assert_eq!(server.workers, None);
+
+    let mut builder = Server::builder();
+    for line in "host=example.com\nport=443\nalias=www.example.com\nworkers=4".lines() {
+        let (key, value) = line.split_once('=').unwrap();
+        builder.set_by_name(key, value).unwrap();
+    }
+    let server = builder.build().unwrap();
+    assert_eq!(server.host, "example.com");
+    assert_eq!(server.port, 443);
+    assert_eq!(server.aliases, vec!["www.example.com"]);
+    assert_eq!(server.workers, Some(4));
+
+    let err = Server::builder().set_by_name("port", "http").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value for `port`: invalid digit found in string"
+    );
+    assert!(Server::builder().set_by_name("hots", "x").is_err());
+}
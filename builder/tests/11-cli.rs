@@ -0,0 +1,78 @@
+// With #[builder(cli)] the struct can be parsed from command line arguments of
+// the form `--field value` or `--field=value`. Underscores in field names are
+// written as dashes, `each` fields are passed once per element under their
+// `each` name, and Option fields may be left out.
+//
+//     impl Command {
+//         pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, Box<dyn Error>>;
+//     }
+//
+// The `--help` text is generated from the doc comments and is also available
+// as CommandBuilder::help(). On `--help`, from_args fails with a CommandHelp
+// error so the caller decides how to print it and exit.
+
+use derive_builder::Builder;
+
+/// Runs a program
+#[derive(Builder)]
+#[builder(cli)]
+pub struct Command {
+    /// The program to run
+    executable: String,
+    /// An argument passed to the program
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    /// Working directory
+    current_dir: Option<String>,
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn main() {
+    let command = Command::from_args(args(&[
+        "cmd",
+        "--executable",
+        "cargo",
+        "--arg",
+        "build",
+        "--arg=--release",
+    ]))
+    .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir, None);
+
+    let command =
+        Command::from_args(args(&["cmd", "--executable=ls", "--current-dir", ".."])).unwrap();
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    let err = Command::from_args(args(&["cmd", "--arg", "x"]))
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "missing field `executable`");
+    let err = Command::from_args(args(&["cmd", "--executable"]))
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "`--executable` requires a value");
+    assert!(Command::from_args(args(&["cmd", "cargo"])).is_err());
+
+    let expected = "\
+Runs a program
+
+Options:
+    --executable <EXECUTABLE>    The program to run
+    --arg <ARG>...               An argument passed to the program (repeatable)
+    --current-dir <CURRENT_DIR>  Working directory (optional)
+    -h, --help                   Print help
+";
+    assert_eq!(CommandBuilder::help(), expected);
+
+    let err = Command::from_args(args(&["cmd", "--executable", "ls", "--help"]))
+        .err()
+        .unwrap();
+    assert!(err.is::<CommandHelp>());
+    assert_eq!(err.to_string(), expected);
+    assert!(!Command::from_args(args(&["cmd"])).err().unwrap().is::<CommandHelp>());
+}
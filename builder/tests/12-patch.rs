@@ -0,0 +1,57 @@
+// Update APIs usually receive a partial version of a struct. With
+// #[builder(patch)] the macro also generates a CommandPatch struct in which
+// every field is optional, and an `apply` method that overwrites only the
+// fields present in the patch. Fields that are already optional are wrapped
+// once more, so that `Some(None)` clears them.
+//
+//     #[derive(Default)]
+//     pub struct CommandPatch {
+//         pub executable: Option<String>,
+//         pub args: Option<Vec<String>>,
+//         pub current_dir: Option<Option<String>>,
+//     }
+//
+//     impl Command {
+//         pub fn apply(&mut self, patch: CommandPatch) {
+//             ...
+//         }
+//     }
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(patch)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let mut command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+
+    command.apply(CommandPatch {
+        args: Some(vec!["test".to_owned()]),
+        current_dir: Some(Some("..".to_owned())),
+        ..Default::default()
+    });
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["test"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    command.apply(CommandPatch::default());
+    assert_eq!(command.args, vec!["test"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    command.apply(CommandPatch {
+        current_dir: Some(None),
+        ..Default::default()
+    });
+    assert_eq!(command.args, vec!["test"]);
+    assert_eq!(command.current_dir, None);
+}
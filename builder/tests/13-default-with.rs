@@ -0,0 +1,57 @@
+// A field may compute its default from the other fields of the builder with
+// #[builder(default_with = "...")]. The closure receives the builder and
+// returns the value the builder stores for the field, i.e. an Option. It only
+// runs in `build()` when the field was not set explicitly, and the computed
+// value is not kept in the builder: the next `build()` computes it again.
+//
+// Defaults that read other defaulted fields name them in
+// #[builder(depends_on = "...")] so that those are computed first.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Endpoint {
+    #[builder(
+        default_with = "|b: &Self| b.address.clone().map(|a| format!(\"http://{}\", a))",
+        depends_on = "address"
+    )]
+    url: String,
+    #[builder(
+        default_with = "|b: &Self| b.host.clone().map(|h| format!(\"{}:{}\", h, b.port.unwrap()))",
+        depends_on = "port"
+    )]
+    address: Option<String>,
+    host: Option<String>,
+    #[builder(default_with = "|_: &Self| Some(80)")]
+    port: u16,
+}
+
+fn main() {
+    let endpoint = Endpoint::builder()
+        .host("example.com".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(endpoint.port, 80);
+    assert_eq!(endpoint.address.as_deref(), Some("example.com:80"));
+    assert_eq!(endpoint.url, "http://example.com:80");
+
+    let endpoint = Endpoint::builder()
+        .host("example.com".to_owned())
+        .port(8080)
+        .url("https://example.com".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(endpoint.address.as_deref(), Some("example.com:8080"));
+    assert_eq!(endpoint.url, "https://example.com");
+
+    let mut builder = Endpoint::builder();
+    builder.host("a".to_owned());
+    assert_eq!(builder.build().unwrap().url, "http://a:80");
+    builder.host("b".to_owned());
+    let endpoint = builder.build().unwrap();
+    assert_eq!(endpoint.address.as_deref(), Some("b:80"));
+    assert_eq!(endpoint.url, "http://b:80");
+
+    let err = Endpoint::builder().build().err().unwrap();
+    assert_eq!(err.to_string(), "missing field `url`");
+}
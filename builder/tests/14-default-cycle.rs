@@ -0,0 +1,20 @@
+// Defaults that depend on each other cannot be ordered, which is reported as
+// a compile error on the `depends_on` attribute.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Endpoint {
+    #[builder(
+        default_with = "|b: &Self| b.port.map(|p| p.to_string())",
+        depends_on = "port"
+    )]
+    host: String,
+    #[builder(
+        default_with = "|b: &Self| b.host.as_ref().map(|_| 80)",
+        depends_on = "host"
+    )]
+    port: u16,
+}
+
+fn main() {}
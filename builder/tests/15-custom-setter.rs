@@ -0,0 +1,44 @@
+// Some fields need a hand-written setter, for example one that parses its
+// argument. With #[builder(setter(custom))] the macro keeps the field in the
+// builder and in `build()` but does not generate a setter, so that the user
+// can write one with the same name in their own impl block.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Request {
+    #[builder(setter(custom))]
+    port: u16,
+    #[builder(setter(custom), each = "header")]
+    headers: Vec<(String, String)>,
+    path: String,
+}
+
+impl RequestBuilder {
+    fn port(&mut self, url: &str) -> &mut Self {
+        self.port = url.rsplit(':').next().and_then(|p| p.parse().ok());
+        self
+    }
+
+    fn header(&mut self, line: &str) -> &mut Self {
+        let (name, value) = line.split_once(": ").unwrap();
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+fn main() {
+    let request = Request::builder()
+        .port("http://localhost:8080")
+        .header("Accept: */*")
+        .path("/".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(request.port, 8080);
+    assert_eq!(
+        request.headers,
+        vec![("Accept".to_owned(), "*/*".to_owned())]
+    );
+    assert_eq!(request.path, "/");
+}
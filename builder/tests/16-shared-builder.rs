@@ -0,0 +1,47 @@
+// Several subsystems may contribute fields from different threads. With
+// #[builder(shared)] the macro generates a CommandSharedBuilder that wraps
+// the builder in Arc<Mutex<..>>, can be cloned into each thread, and has
+// setters taking &self. Its `build()` works on a snapshot of the fields set so
+// far.
+
+use derive_builder::Builder;
+use std::thread;
+
+#[derive(Builder)]
+#[builder(shared)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let builder = Command::shared_builder();
+
+    let handles: Vec<_> = ["build", "--release"]
+        .iter()
+        .map(|arg| {
+            let builder = builder.clone();
+            thread::spawn(move || {
+                builder.arg(arg.to_string());
+            })
+        })
+        .collect();
+    builder.executable("cargo".to_owned());
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let command = builder.build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    let mut args = command.args;
+    args.sort();
+    assert_eq!(args, vec!["--release", "build"]);
+
+    builder.with(|b| {
+        b.current_dir("..".to_owned());
+    });
+    let command = builder.build().unwrap();
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+}
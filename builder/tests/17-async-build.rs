@@ -0,0 +1,89 @@
+// With #[builder(async_build)], `build` becomes an async function consuming
+// the builder, so that `default_with` closures and validators can be async.
+// Only `async` closures are awaited, and an async closure may borrow the
+// builder across its awaits. The generated code only awaits futures; it does
+// not depend on any executor.
+//
+//     impl CommandBuilder {
+//         pub async fn build(self) -> Result<Command, Box<dyn Error>>;
+//     }
+//
+// A field-level #[builder(validate = "path")] names a function taking a
+// reference to the field's value and returning Result<(), E>, where E converts
+// into Box<dyn Error>. #[builder(validate_async = "path")] names an async one,
+// which requires async_build.
+
+use derive_builder::Builder;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+async fn read_secret(name: &str) -> Option<String> {
+    Some(format!("secret-for-{}", name))
+}
+
+async fn non_empty(value: &String) -> Result<(), String> {
+    if value.is_empty() {
+        Err("user must not be empty".to_owned())
+    } else {
+        Ok(())
+    }
+}
+
+fn check_port(port: &u16) -> Result<(), &'static str> {
+    if *port == 0 {
+        Err("port must not be 0")
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Builder)]
+#[builder(async_build)]
+pub struct Credentials {
+    #[builder(validate_async = "non_empty")]
+    user: String,
+    #[builder(default_with = "async |b: &Self| read_secret(b.user.as_deref()?).await")]
+    password: String,
+    #[builder(default_with = "|_: &Self| Some(443)", validate = "check_port")]
+    port: u16,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(validate = "check_port")]
+    port: u16,
+}
+
+fn main() {
+    let mut builder = Credentials::builder();
+    builder.user("admin".to_owned());
+    let credentials = block_on(builder.build()).unwrap();
+    assert_eq!(credentials.user, "admin");
+    assert_eq!(credentials.password, "secret-for-admin");
+    assert_eq!(credentials.port, 443);
+
+    let mut builder = Credentials::builder();
+    builder.user(String::new());
+    let err = block_on(builder.build()).err().unwrap();
+    assert_eq!(err.to_string(), "user must not be empty");
+
+    let mut builder = Credentials::builder();
+    builder.user("admin".to_owned()).port(0);
+    let err = block_on(builder.build()).err().unwrap();
+    assert_eq!(err.to_string(), "port must not be 0");
+
+    let err = Server::builder().port(0).build().err().unwrap();
+    assert_eq!(err.to_string(), "port must not be 0");
+    assert_eq!(Server::builder().port(80).build().unwrap().port, 80);
+}
@@ -0,0 +1,41 @@
+// Partially built configurations are easier to log and diff with
+// #[builder(describe)], which adds a `describe` method listing every field of
+// the builder rendered with Debug, and a Display impl printing one stable
+// `key = value` line per field.
+//
+//     impl CommandBuilder {
+//         pub fn describe(&self) -> Vec<(&'static str, Option<String>)>;
+//     }
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(describe)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder
+        .executable("cargo".to_owned())
+        .arg("build".to_owned());
+
+    assert_eq!(
+        builder.describe(),
+        vec![
+            ("executable", Some(r#""cargo""#.to_owned())),
+            ("args", Some(r#"["build"]"#.to_owned())),
+            ("current_dir", None),
+        ],
+    );
+
+    let expected = r#"executable = "cargo"
+args = ["build"]
+current_dir = <unset>
+"#;
+    assert_eq!(builder.to_string(), expected);
+}
@@ -0,0 +1,37 @@
+// Renamed fields can keep their old setter names for a while with
+// #[builder(alias = "...")]. Together with #[builder(deprecated = "...")] the
+// alias setters are marked #[deprecated], and forward to the canonical setter.
+// Aliases are also accepted as keys by `set_by_name`, and generated on the
+// shared builder.
+
+#![allow(deprecated)]
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(from_env, shared)]
+pub struct Command {
+    #[builder(alias = "program", alias = "exe", deprecated = "use `executable`")]
+    executable: String,
+    #[builder(each = "arg", alias = "arguments")]
+    args: Vec<String>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .program("cargo".to_owned())
+        .arguments(vec!["build".to_owned()])
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+
+    let mut builder = Command::builder();
+    builder.set_by_name("exe", "rustc").unwrap();
+    assert_eq!(builder.build().unwrap().executable, "rustc");
+
+    let shared = Command::shared_builder();
+    shared.exe("rustdoc".to_owned());
+    assert_eq!(shared.build().unwrap().executable, "rustdoc");
+}
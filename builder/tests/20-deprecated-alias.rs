@@ -0,0 +1,16 @@
+// Calling a deprecated alias setter triggers the `deprecated` lint with the
+// note given in the attribute.
+
+#![deny(deprecated)]
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(alias = "program", deprecated = "use `executable`")]
+    executable: String,
+}
+
+fn main() {
+    let _ = Command::builder().program("cargo".to_owned()).build();
+}
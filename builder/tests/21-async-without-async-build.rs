@@ -0,0 +1,20 @@
+// Async validators are awaited by the async `build` of #[builder(async_build)],
+// so they are rejected on other builders.
+
+use derive_builder::Builder;
+
+async fn non_empty(value: &String) -> Result<(), String> {
+    if value.is_empty() {
+        Err("user must not be empty".to_owned())
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Builder)]
+pub struct Credentials {
+    #[builder(validate_async = "non_empty")]
+    user: String,
+}
+
+fn main() {}
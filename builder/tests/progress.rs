@@ -7,7 +7,19 @@ fn tests() {
     t.pass("tests/04-call-build.rs");
     t.pass("tests/05-method-chaining.rs");
     t.pass("tests/06-optional-field.rs");
-    // t.pass("tests/07-repeated-field.rs");
+    t.pass("tests/07-repeated-field.rs");
     //t.compile_fail("tests/08-unrecognized-attribute.rs");
     //t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-from-env.rs");
+    t.pass("tests/11-cli.rs");
+    t.pass("tests/12-patch.rs");
+    t.pass("tests/13-default-with.rs");
+    t.compile_fail("tests/14-default-cycle.rs");
+    t.pass("tests/15-custom-setter.rs");
+    t.pass("tests/16-shared-builder.rs");
+    t.pass("tests/17-async-build.rs");
+    t.pass("tests/18-describe.rs");
+    t.pass("tests/19-alias.rs");
+    t.compile_fail("tests/20-deprecated-alias.rs");
+    t.compile_fail("tests/21-async-without-async-build.rs");
 }
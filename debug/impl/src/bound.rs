@@ -0,0 +1,75 @@
+//! Inference of the where-clause of generated impls: only the type parameters (or their
+//! associated types) that are actually formatted get a bound, and only for the formatting
+//! trait that is used.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::visit::{self, Visit};
+use syn::{Generics, Ident, Path, Type, TypePath, WherePredicate};
+
+/// Collects the types to bound: `T` for a type parameter used directly, `T::Assoc` or
+/// `<T as Trait>::Assoc` for an associated type path. Nothing inside `PhantomData<..>` is
+/// formatted, so it is skipped.
+struct Collector<'a> {
+    params: Vec<&'a Ident>,
+    types: Vec<TypePath>,
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_type_path(&mut self, ty: &'ast TypePath) {
+        if let Some(qself) = &ty.qself {
+            let mut inner = Collector {
+                params: self.params.clone(),
+                types: Vec::new(),
+            };
+            inner.visit_type(&qself.ty);
+            if !inner.types.is_empty() {
+                self.types.push(ty.clone());
+                return;
+            }
+        } else {
+            let segments = &ty.path.segments;
+            if ty.path.leading_colon.is_none() {
+                if let Some(first) = segments.first() {
+                    if self.params.contains(&&first.ident) {
+                        self.types.push(ty.clone());
+                        return;
+                    }
+                }
+            }
+            if segments.last().is_some_and(|s| s.ident == "PhantomData") {
+                return;
+            }
+        }
+        visit::visit_type_path(self, ty);
+    }
+}
+
+/// The bounds needed to format values of the given field types with the given traits, e.g.
+/// `T: std::fmt::Debug`, `T::Value: std::fmt::Binary`, or `K: std::cmp::Ord` to sort keys
+pub fn infer<'a>(
+    generics: &Generics,
+    formatted: impl IntoIterator<Item = (&'a Type, Path)>,
+) -> Vec<WherePredicate> {
+    let mut bounds: Vec<WherePredicate> = Vec::new();
+    for (ty, trait_path) in formatted {
+        let mut collector = Collector {
+            params: generics.type_params().map(|p| &p.ident).collect(),
+            types: Vec::new(),
+        };
+        collector.visit_type(ty);
+        for bounded in collector.types {
+            let bound: WherePredicate = syn::parse_quote!(#bounded: #trait_path);
+            if !bounds.iter().any(|b| same_tokens(b, &bound)) {
+                bounds.push(bound);
+            }
+        }
+    }
+    bounds
+}
+
+fn same_tokens(a: &impl quote::ToTokens, b: &impl quote::ToTokens) -> bool {
+    let a: TokenStream = quote!(#a);
+    let b: TokenStream = quote!(#b);
+    a.to_string() == b.to_string()
+}
@@ -0,0 +1,224 @@
+//! The CustomDisplay derive: a #[display = "..."] format string on the struct, or on each
+//! variant of an enum, with placeholders referring to the fields by name, or by index for
+//! tuple fields. Unit variants without one display as their name.
+
+use crate::format::{self, Arg, FormatString};
+use crate::{attr_metas, bound, parse_bound, AttrMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::*;
+
+/// The #[display ...] attributes of the type or of a variant
+struct DisplayAttrs {
+    /// Set by #[display = "..."]
+    format: Option<FormatString>,
+    /// Set by #[display(bound = "...")] on the type: replaces the inferred bounds
+    bound: Option<Vec<WherePredicate>>,
+}
+
+impl DisplayAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut format = None;
+        let mut bound = None;
+        for meta in attr_metas(attrs, "display")? {
+            match meta {
+                AttrMeta::Format(lit) => format = Some(FormatString::parse(&lit)?),
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("bound") => {
+                    bound = Some(parse_bound(&lit)?);
+                }
+                AttrMeta::Nested(meta) => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected `display = \"...\"` or `display(bound = \"...\")`",
+                    ))
+                }
+            }
+        }
+        Ok(DisplayAttrs { format, bound })
+    }
+}
+
+/// One arm of the generated `match self`, and the fields it formats with their traits
+fn expand_arm<'a>(
+    path: TokenStream,
+    ident: &Ident,
+    fields: &'a Fields,
+    format: Option<&FormatString>,
+) -> Result<(TokenStream, Vec<(&'a Type, Path)>)> {
+    let bindings = (0..fields.len())
+        .map(|i| format_ident!("__self_{}", i))
+        .collect::<Vec<_>>();
+    let pattern = match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote!(#path { #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+        Fields::Unit => quote!(#path),
+    };
+    let format = match (format, fields) {
+        (Some(format), _) => format,
+        (None, Fields::Unit) => {
+            let name = ident.to_string();
+            return Ok((quote!(#pattern => f.write_str(#name),), Vec::new()));
+        }
+        (None, _) => {
+            return Err(Error::new_spanned(
+                ident,
+                "missing #[display = \"...\"] attribute",
+            ))
+        }
+    };
+
+    // The field of each placeholder, in the same order
+    let mut next = 0;
+    let mut indices = Vec::new();
+    for placeholder in &format.placeholders {
+        let index = match (&placeholder.arg, fields) {
+            (Arg::Name(name), Fields::Named(_)) => fields
+                .iter()
+                .position(|f| f.ident.as_ref().unwrap().unraw() == name),
+            (Arg::Index(index), Fields::Unnamed(_)) => Some(*index),
+            (Arg::Next, Fields::Unnamed(_)) => {
+                next += 1;
+                Some(next - 1)
+            }
+            _ => None,
+        };
+        match index {
+            Some(index) if index < fields.len() => indices.push(index),
+            _ => {
+                let message = match (&placeholder.arg, fields) {
+                    (Arg::Name(name), Fields::Named(_)) => format!("no field `{}`", name),
+                    (Arg::Index(index), Fields::Unnamed(_)) => format!("no field `{}`", index),
+                    (_, Fields::Named(_)) => "expected a field name".to_owned(),
+                    (_, Fields::Unnamed(_)) => "expected a field index".to_owned(),
+                    (_, Fields::Unit) => "there are no fields to format".to_owned(),
+                };
+                return Err(format::error(
+                    &format.lit,
+                    placeholder.range.clone(),
+                    message,
+                ));
+            }
+        }
+    }
+
+    let args = indices
+        .iter()
+        .map(|&i| bindings[i].clone())
+        .collect::<Vec<_>>();
+    let lit = format.with_args(&args);
+    let mut used = indices.clone();
+    used.sort_unstable();
+    used.dedup();
+    let used = used.iter().map(|&i| &bindings[i]);
+    let arm = quote! {
+        #pattern => f.write_fmt(format_args!(#lit #(, #used = #used)*)),
+    };
+
+    let field_types = fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let formatted = indices
+        .iter()
+        .zip(&format.placeholders)
+        .map(|(&i, placeholder)| (field_types[i], placeholder.trait_path()))
+        .collect();
+    Ok((arm, formatted))
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let type_name = &input.ident;
+    let container = DisplayAttrs::parse(&input.attrs)?;
+    let mut arms = Vec::new();
+    let mut formatted = Vec::new();
+    match &input.data {
+        Data::Struct(data) => {
+            let (arm, types) = expand_arm(
+                quote!(Self),
+                type_name,
+                &data.fields,
+                container.format.as_ref(),
+            )?;
+            arms.push(arm);
+            formatted.extend(types);
+        }
+        Data::Enum(data) => {
+            if let Some(format) = &container.format {
+                return Err(Error::new_spanned(
+                    &format.lit,
+                    "#[display = \"...\"] goes on each variant of an enum",
+                ));
+            }
+            for variant in &data.variants {
+                let attrs = DisplayAttrs::parse(&variant.attrs)?;
+                if let Some(bound) = attrs.bound {
+                    return Err(Error::new_spanned(
+                        &bound[0],
+                        "#[display(bound = \"...\")] goes on the enum",
+                    ));
+                }
+                let ident = &variant.ident;
+                let (arm, types) = expand_arm(
+                    quote!(Self::#ident),
+                    ident,
+                    &variant.fields,
+                    attrs.format.as_ref(),
+                )?;
+                arms.push(arm);
+                formatted.extend(types);
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input,
+                "CustomDisplay does not support unions",
+            ))
+        }
+    }
+
+    // impl std::fmt::Display for Endpoint {
+    //     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    //         match self {
+    //             Self {
+    //                 host: __self_0,
+    //                 port: __self_1,
+    //             } => f.write_fmt(format_args!(
+    //                 "{__self_0}:{__self_1}",
+    //                 __self_0 = __self_0,
+    //                 __self_1 = __self_1
+    //             )),
+    //         }
+    //     }
+    // }
+    let body = if arms.is_empty() {
+        quote!(match *self {})
+    } else {
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    };
+
+    // where T: std::fmt::Display, ... for the placeholders that format a type parameter
+    let mut generics = input.generics.clone();
+    let bounds = match container.bound {
+        Some(bound) => bound,
+        None => bound::infer(&input.generics, formatted),
+    };
+    generics.make_where_clause().predicates.extend(bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics std::fmt::Display for #type_name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,209 @@
+//! Parsing of the format strings given in `#[debug = "..."]` and `#[display = "..."]`,
+//! following the grammar of `std::fmt`, so that mistakes are reported on the attribute
+//! instead of deep inside `format_args!`.
+
+use proc_macro2::Span;
+use std::ops::Range;
+use syn::{parse_quote, Error, Ident, LitStr, Path, Result};
+
+/// The argument a placeholder refers to: `{}`, `{0}` or `{name}`
+#[derive(Debug, PartialEq)]
+pub enum Arg {
+    Next,
+    Index(usize),
+    Name(String),
+}
+
+/// One `{arg:spec}` of a format string
+pub struct Placeholder {
+    pub arg: Arg,
+    /// The formatting trait selected by the spec, e.g. `Debug` for `{:?}`
+    pub trait_name: &'static str,
+    /// Byte range of the placeholder within the string, braces included
+    pub range: Range<usize>,
+}
+
+/// A parsed format string literal
+pub struct FormatString {
+    pub lit: LitStr,
+    pub placeholders: Vec<Placeholder>,
+}
+
+impl FormatString {
+    pub fn parse(lit: &LitStr) -> Result<Self> {
+        let value = lit.value();
+        let mut placeholders = Vec::new();
+        let mut chars = value.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '{' if matches!(chars.peek(), Some((_, '{'))) => {
+                    chars.next();
+                }
+                '}' if matches!(chars.peek(), Some((_, '}'))) => {
+                    chars.next();
+                }
+                '{' => {
+                    let end = match value[start..].find('}') {
+                        Some(len) => start + len + 1,
+                        None => return Err(error(lit, start..value.len(), "unmatched `{`")),
+                    };
+                    while chars.next_if(|&(i, _)| i < end).is_some() {}
+                    placeholders.push(parse_placeholder(
+                        lit,
+                        &value[start + 1..end - 1],
+                        start..end,
+                    )?);
+                }
+                '}' => return Err(error(lit, start..start + 1, "unmatched `}`")),
+                _ => {}
+            }
+        }
+        Ok(FormatString {
+            lit: lit.clone(),
+            placeholders,
+        })
+    }
+
+    /// Checks that the string formats exactly one value, referred to as `{}` or `{0}`
+    pub fn expect_single_value(&self) -> Result<()> {
+        match self.placeholders.as_slice() {
+            [placeholder] => match placeholder.arg {
+                Arg::Next | Arg::Index(0) => Ok(()),
+                _ => Err(error(
+                    &self.lit,
+                    placeholder.range.clone(),
+                    "expected `{}` or `{0}` referring to the field",
+                )),
+            },
+            [] => Err(Error::new_spanned(
+                &self.lit,
+                "format string must contain one `{}` for the field",
+            )),
+            [_, extra, ..] => Err(error(
+                &self.lit,
+                extra.range.clone(),
+                "format string must contain exactly one `{}` for the field",
+            )),
+        }
+    }
+
+    /// The formatting trait of the only placeholder, see `expect_single_value`
+    pub fn trait_path(&self) -> Path {
+        self.placeholders[0].trait_path()
+    }
+
+    /// The literal with the argument of each placeholder replaced, e.g. `{0:x}` by `{arg:x}`
+    pub fn with_args(&self, args: &[Ident]) -> LitStr {
+        let value = self.lit.value();
+        let mut rewritten = String::new();
+        let mut end = 0;
+        for (placeholder, arg) in self.placeholders.iter().zip(args) {
+            let range = placeholder.range.clone();
+            rewritten += &value[end..range.start];
+            rewritten += &format!("{{{}", arg);
+            if let Some((_, spec)) = value[range.start + 1..range.end - 1].split_once(':') {
+                rewritten += &format!(":{}", spec);
+            }
+            rewritten += "}";
+            end = range.end;
+        }
+        rewritten += &value[end..];
+        LitStr::new(&rewritten, self.lit.span())
+    }
+}
+
+impl Placeholder {
+    /// `std::fmt::Debug`, `std::fmt::Display`, ...
+    pub fn trait_path(&self) -> Path {
+        let ident = Ident::new(self.trait_name, Span::call_site());
+        parse_quote!(std::fmt::#ident)
+    }
+}
+
+/// Parses the inside of `{...}`: `[arg][:[[fill]align][sign]['#']['0'][width]['.' precision]type]`
+fn parse_placeholder(lit: &LitStr, inner: &str, range: Range<usize>) -> Result<Placeholder> {
+    let (arg, spec) = inner.split_once(':').unwrap_or((inner, ""));
+    let arg = match arg.trim() {
+        "" => Arg::Next,
+        arg if arg.bytes().all(|b| b.is_ascii_digit()) => Arg::Index(arg.parse().unwrap()),
+        arg if is_identifier(arg) => Arg::Name(arg.to_owned()),
+        _ => return Err(error(lit, range, "invalid argument in format placeholder")),
+    };
+
+    let mut rest = spec;
+    // [[fill]align]
+    let mut spec_chars = rest.chars();
+    let first = spec_chars.next();
+    let second = spec_chars.next();
+    if matches!(second, Some('<' | '^' | '>')) {
+        rest = &rest[first.unwrap().len_utf8() + 1..];
+    } else if matches!(first, Some('<' | '^' | '>')) {
+        rest = &rest[1..];
+    }
+    // [sign]['#']['0']
+    rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    rest = rest.strip_prefix('#').unwrap_or(rest);
+    rest = rest.strip_prefix('0').unwrap_or(rest);
+    // [width]['.' precision]
+    rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if let Some(precision) = rest.strip_prefix('.') {
+        rest = precision.trim_start_matches(|c: char| c.is_ascii_digit());
+        if rest.len() == precision.len() {
+            return Err(error(
+                lit,
+                range,
+                "expected a number after `.`; `*` is not supported",
+            ));
+        }
+    }
+    if rest.contains('$') {
+        return Err(error(
+            lit,
+            range,
+            "width and precision arguments (`$`) are not supported",
+        ));
+    }
+    let trait_name = match rest {
+        "" => "Display",
+        "?" | "x?" | "X?" => "Debug",
+        "x" => "LowerHex",
+        "X" => "UpperHex",
+        "o" => "Octal",
+        "b" => "Binary",
+        "e" => "LowerExp",
+        "E" => "UpperExp",
+        "p" => "Pointer",
+        _ => {
+            return Err(error(
+                lit,
+                range,
+                format!("unsupported format spec `{}`", spec),
+            ))
+        }
+    };
+
+    Ok(Placeholder {
+        arg,
+        trait_name,
+        range,
+    })
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_alphabetic())
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+}
+
+/// An error pointing at `range` within the literal, where the compiler supports it, or at
+/// the whole literal otherwise
+pub fn error(lit: &LitStr, range: Range<usize>, message: impl std::fmt::Display) -> Error {
+    // Byte offsets into the value only match the source when the literal has no escapes.
+    let source = lit.token().to_string();
+    let span = if source[1..source.len() - 1] == lit.value() {
+        lit.token().subspan(range.start + 1..range.end + 1)
+    } else {
+        None
+    };
+    Error::new(span.unwrap_or_else(|| lit.span()), message)
+}
@@ -0,0 +1,355 @@
+//! Adapters emitted into the generated `fmt` for fields that need more than `format_args!`.
+//! Each is a private item of the function body, so only the ones in use are generated.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{GenericArgument, PathArguments, Type};
+
+/// The shape a collection is printed in
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Collection {
+    List,
+    Set,
+    Map,
+}
+
+impl Collection {
+    /// Recognizes the standard collections by name, e.g. `Vec<T>`, `HashMap<K, V>`, `&[T]`
+    pub fn of(ty: &Type) -> Option<Self> {
+        match ty {
+            Type::Reference(ty) => Collection::of(&ty.elem),
+            Type::Slice(_) | Type::Array(_) => Some(Collection::List),
+            Type::Path(ty) => match ty.path.segments.last()?.ident.to_string().as_str() {
+                "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap" => Some(Collection::List),
+                "HashSet" | "BTreeSet" => Some(Collection::Set),
+                "HashMap" | "BTreeMap" => Some(Collection::Map),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The presets for byte buffers
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Encoding {
+    /// `0xdead`
+    Hex,
+    /// Like `Hex`, or one line per 16 bytes with offset, hex and ASCII columns in `{:#?}`
+    HexDump,
+    /// `"3q0="`, the standard alphabet with padding
+    Base64,
+}
+
+/// The type of the items of a collection type, or of the keys of a map, e.g. `T` of `Vec<T>`
+pub fn item_type(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(ty) => item_type(&ty.elem),
+        Type::Slice(ty) => Some(&ty.elem),
+        Type::Array(ty) => Some(&ty.elem),
+        Type::Path(ty) => match &ty.path.segments.last()?.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Helper {
+    /// `__DebugList`, `__DebugSet` or `__DebugMap { collection, limit }`, and the same
+    /// prefixed with `Sorted`, which print the items in order
+    Items { kind: Collection, sorted: bool },
+    /// `__DebugStr { string, limit }`
+    Str,
+    /// `__DebugWith { value, fmt }`
+    With,
+    /// `__DebugHex`, `__DebugHexDump` or `__DebugBase64 { bytes }`
+    Bytes(Encoding),
+}
+
+impl Helper {
+    /// The name of the adapter
+    pub fn ident(self) -> TokenStream {
+        match self {
+            Helper::Items { kind, sorted } => {
+                let sorted = if sorted { "Sorted" } else { "" };
+                let kind = match kind {
+                    Collection::List => "List",
+                    Collection::Set => "Set",
+                    Collection::Map => "Map",
+                };
+                let ident = format_ident!("__Debug{}{}", sorted, kind);
+                quote!(#ident)
+            }
+            Helper::Str => quote!(__DebugStr),
+            Helper::With => quote!(__DebugWith),
+            Helper::Bytes(Encoding::Hex) => quote!(__DebugHex),
+            Helper::Bytes(Encoding::HexDump) => quote!(__DebugHexDump),
+            Helper::Bytes(Encoding::Base64) => quote!(__DebugBase64),
+        }
+    }
+
+    /// The definition of the adapter
+    pub fn expand(self) -> TokenStream {
+        let ident = self.ident();
+        match self {
+            // struct __DebugList<'a, T: ?Sized> {
+            //     collection: &'a T,
+            //     limit: usize,
+            // }
+            //
+            // impl<'a, T: ?Sized> std::fmt::Debug for __DebugList<'a, T>
+            // where
+            //     &'a T: IntoIterator,
+            //     <&'a T as IntoIterator>::Item: std::fmt::Debug,
+            // {
+            //     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            //         let mut items = self.collection.into_iter();
+            //         let mut list = f.debug_list();
+            //         list.entries(items.by_ref().take(self.limit));
+            //         let more = items.count();
+            //         if more > 0 {
+            //             list.entry(&format_args!("... ({} more)", more));
+            //         }
+            //         list.finish()
+            //     }
+            // }
+            //
+            // where the sorted variants also require `Ord` and collect the items to sort them
+            // first
+            Helper::Items {
+                kind: kind @ (Collection::List | Collection::Set),
+                sorted,
+            } => {
+                let debug = match kind {
+                    Collection::List => quote!(debug_list),
+                    _ => quote!(debug_set),
+                };
+                let (bound, items) = if sorted {
+                    let items = quote! {
+                        let mut items = self.collection.into_iter().collect::<Vec<_>>();
+                        items.sort();
+                        let mut items = items.into_iter();
+                    };
+                    (quote!(std::fmt::Debug + std::cmp::Ord), items)
+                } else {
+                    let items = quote!(let mut items = self.collection.into_iter(););
+                    (quote!(std::fmt::Debug), items)
+                };
+                quote! {
+                    struct #ident<'a, T: ?Sized> {
+                        collection: &'a T,
+                        limit: usize,
+                    }
+
+                    impl<'a, T: ?Sized> std::fmt::Debug for #ident<'a, T>
+                    where
+                        &'a T: IntoIterator,
+                        <&'a T as IntoIterator>::Item: #bound,
+                    {
+                        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                            #items
+                            let mut list = f.#debug();
+                            list.entries(items.by_ref().take(self.limit));
+                            let more = items.count();
+                            if more > 0 {
+                                list.entry(&format_args!("... ({} more)", more));
+                            }
+                            list.finish()
+                        }
+                    }
+                }
+            }
+            // Like `__DebugSet`, with the entries printed as `key: value`, which renders the
+            // same as `debug_map` but leaves room for the trailing count. Sorted maps are
+            // sorted by key.
+            Helper::Items {
+                kind: Collection::Map,
+                sorted,
+            } => {
+                let (bound, entries) = if sorted {
+                    let entries = quote! {
+                        let mut entries = self.collection.into_iter().collect::<Vec<_>>();
+                        entries.sort_by(|a, b| a.0.cmp(&b.0));
+                        let mut entries = entries.into_iter();
+                    };
+                    (quote!(std::fmt::Debug + std::cmp::Ord), entries)
+                } else {
+                    let entries = quote!(let mut entries = self.collection.into_iter(););
+                    (quote!(std::fmt::Debug), entries)
+                };
+                quote! {
+                struct #ident<'a, T: ?Sized> {
+                    collection: &'a T,
+                    limit: usize,
+                }
+
+                impl<'a, T: ?Sized, K, V> std::fmt::Debug for #ident<'a, T>
+                where
+                    &'a T: IntoIterator<Item = (K, V)>,
+                    K: #bound,
+                    V: std::fmt::Debug,
+                {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        struct Entry<K, V>(K, V);
+
+                        impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for Entry<K, V> {
+                            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                                std::fmt::Debug::fmt(&self.0, f)?;
+                                f.write_str(": ")?;
+                                std::fmt::Debug::fmt(&self.1, f)
+                            }
+                        }
+
+                        #entries
+                        let mut map = f.debug_set();
+                        map.entries(entries.by_ref().take(self.limit).map(|(k, v)| Entry(k, v)));
+                        let more = entries.count();
+                        if more > 0 {
+                            map.entry(&format_args!("... ({} more)", more));
+                        }
+                        map.finish()
+                    }
+                }
+                }
+            }
+            // "the first `limit` characters"... (N more)
+            Helper::Str => quote! {
+                struct #ident<'a> {
+                    string: &'a str,
+                    limit: usize,
+                }
+
+                impl<'a> std::fmt::Debug for #ident<'a> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        match self.string.char_indices().nth(self.limit) {
+                            Some((end, _)) => {
+                                std::fmt::Debug::fmt(&self.string[..end], f)?;
+                                let more = self.string[end..].chars().count();
+                                write!(f, "... ({} more)", more)
+                            }
+                            None => std::fmt::Debug::fmt(self.string, f),
+                        }
+                    }
+                }
+            },
+            // Calls the function given by #[debug(with = "...")]
+            Helper::With => quote! {
+                struct #ident<'a, T: ?Sized> {
+                    value: &'a T,
+                    fmt: fn(&T, &mut std::fmt::Formatter) -> std::fmt::Result,
+                }
+
+                impl<'a, T: ?Sized> std::fmt::Debug for #ident<'a, T> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        (self.fmt)(self.value, f)
+                    }
+                }
+            },
+            Helper::Bytes(Encoding::Hex) => quote! {
+                struct #ident<'a> {
+                    bytes: &'a [u8],
+                }
+
+                impl<'a> std::fmt::Debug for #ident<'a> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("0x")?;
+                        for byte in self.bytes {
+                            write!(f, "{:02x}", byte)?;
+                        }
+                        Ok(())
+                    }
+                }
+            },
+            // 00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|
+            //
+            // with each line an entry of a `debug_list`, so that it is indented along with
+            // the rest of the output
+            Helper::Bytes(Encoding::HexDump) => quote! {
+                struct #ident<'a> {
+                    bytes: &'a [u8],
+                }
+
+                impl<'a> std::fmt::Debug for #ident<'a> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        struct Line<'a> {
+                            offset: usize,
+                            bytes: &'a [u8],
+                        }
+
+                        impl<'a> std::fmt::Debug for Line<'a> {
+                            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                                write!(f, "{:08x} ", self.offset)?;
+                                for i in 0..16 {
+                                    if i == 8 {
+                                        f.write_str(" ")?;
+                                    }
+                                    match self.bytes.get(i) {
+                                        Some(byte) => write!(f, " {:02x}", byte)?,
+                                        None => f.write_str("   ")?,
+                                    }
+                                }
+                                f.write_str("  |")?;
+                                for &byte in self.bytes {
+                                    let c = if byte.is_ascii_graphic() || byte == b' ' {
+                                        byte as char
+                                    } else {
+                                        '.'
+                                    };
+                                    write!(f, "{}", c)?;
+                                }
+                                f.write_str("|")
+                            }
+                        }
+
+                        if !f.alternate() {
+                            f.write_str("0x")?;
+                            for byte in self.bytes {
+                                write!(f, "{:02x}", byte)?;
+                            }
+                            return Ok(());
+                        }
+                        f.debug_list()
+                            .entries(self.bytes.chunks(16).enumerate().map(|(i, bytes)| Line {
+                                offset: i * 16,
+                                bytes,
+                            }))
+                            .finish()
+                    }
+                }
+            },
+            Helper::Bytes(Encoding::Base64) => quote! {
+                struct #ident<'a> {
+                    bytes: &'a [u8],
+                }
+
+                impl<'a> std::fmt::Debug for #ident<'a> {
+                    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        const ALPHABET: &[u8; 64] =
+                            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+                        f.write_str("\"")?;
+                        for chunk in self.bytes.chunks(3) {
+                            let n = chunk
+                                .iter()
+                                .enumerate()
+                                .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+                            for i in 0..4 {
+                                if i <= chunk.len() {
+                                    let index = (n >> (18 - 6 * i)) & 0x3f;
+                                    write!(f, "{}", ALPHABET[index as usize] as char)?;
+                                } else {
+                                    f.write_str("=")?;
+                                }
+                            }
+                        }
+                        f.write_str("\"")
+                    }
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,979 @@
+mod bound;
+mod display;
+mod format;
+mod helper;
+
+use crate::format::FormatString;
+use crate::helper::{item_type, Collection, Encoding, Helper};
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::*;
+
+/// One item of the #[debug ...] or #[display ...] attributes: the literal of
+/// #[debug = "..."], or one of the comma-separated metas of #[debug(...)]
+enum AttrMeta {
+    Format(LitStr),
+    Nested(NestedMeta),
+}
+
+fn attr_metas(attrs: &[Attribute], name: &str) -> Result<Vec<AttrMeta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(lit), ..
+            }) => metas.push(AttrMeta::Format(lit)),
+            Meta::List(list) => metas.extend(list.nested.into_iter().map(AttrMeta::Nested)),
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    format!("expected `{0} = \"...\"` or `{0}(...)`", name),
+                ))
+            }
+        }
+    }
+    Ok(metas)
+}
+
+/// Parses the where-predicates of `bound = "..."`
+fn parse_bound(lit: &LitStr) -> Result<Vec<WherePredicate>> {
+    let bound = lit.parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+    Ok(bound.into_iter().collect())
+}
+
+/// Parses the number of `max_items = N` or `max_len = N`
+fn parse_limit(lit: &Lit) -> Result<usize> {
+    match lit {
+        Lit::Int(int) => int.base10_parse(),
+        _ => Err(Error::new_spanned(lit, "expected an integer")),
+    }
+}
+
+/// The #[debug(...)] attributes of the struct
+struct ContainerAttrs {
+    /// Set by #[debug(bound = "...")]: replaces the inferred bounds
+    bound: Option<Vec<WherePredicate>>,
+    /// Set by #[debug(max_items = N)]: the default limit of the fields holding a standard
+    /// collection
+    max_items: Option<usize>,
+    /// Set by #[debug(depth_limit = N)]: how deeply values of this type may nest before
+    /// printing `...`
+    depth_limit: Option<usize>,
+    /// Set by #[debug(detect_cycles)]: print `<cycle>` for a value that is already being
+    /// formatted further up, e.g. through an `Rc`
+    detect_cycles: bool,
+    /// Set by #[debug(order = "alphabetical")], as opposed to "declared": print named
+    /// fields sorted by name
+    alphabetical: bool,
+}
+
+const CONTAINER_EXPECTED: &str = "expected one of `debug(bound = \"...\")`, \
+    `debug(max_items = N)`, `debug(depth_limit = N)`, `debug(detect_cycles)`, \
+    `debug(order = \"...\")`";
+
+impl ContainerAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut bound = None;
+        let mut max_items = None;
+        let mut depth_limit = None;
+        let mut detect_cycles = false;
+        let mut alphabetical = false;
+        for meta in attr_metas(attrs, "debug")? {
+            match meta {
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("bound") => {
+                    bound = Some(parse_bound(&lit)?);
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit,
+                    ..
+                }))) if path.is_ident("max_items") => {
+                    max_items = Some(parse_limit(&lit)?);
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit,
+                    ..
+                }))) if path.is_ident("depth_limit") => {
+                    depth_limit = Some(parse_limit(&lit)?);
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path)))
+                    if path.is_ident("detect_cycles") =>
+                {
+                    detect_cycles = true;
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("order") => {
+                    alphabetical = match lit.value().as_str() {
+                        "alphabetical" => true,
+                        "declared" => false,
+                        _ => {
+                            return Err(Error::new_spanned(
+                                lit,
+                                "expected \"alphabetical\" or \"declared\"",
+                            ))
+                        }
+                    };
+                }
+                AttrMeta::Format(lit) => {
+                    return Err(Error::new_spanned(lit, CONTAINER_EXPECTED));
+                }
+                AttrMeta::Nested(meta) => {
+                    return Err(Error::new_spanned(meta, CONTAINER_EXPECTED));
+                }
+            }
+        }
+        Ok(ContainerAttrs {
+            bound,
+            max_items,
+            depth_limit,
+            detect_cycles,
+            alphabetical,
+        })
+    }
+}
+
+/// How a field is rendered
+enum Style {
+    /// With its Debug impl, the default
+    Debug,
+    /// #[debug = "..."]
+    Format(FormatString),
+    /// #[debug(skip)]: left out, and the output marked non-exhaustive
+    Skip,
+    /// #[debug(redact)] prints `<redacted>`, #[debug(redact(len))] `<redacted len=N>`
+    Redact { len: bool },
+    /// #[debug(max_items = N)], on the field or as the struct's default for collections,
+    /// and/or #[debug(sorted)]. `derefs` is the number of references around the collection
+    /// in the field's type.
+    Items {
+        kind: Collection,
+        limit: Option<usize>,
+        sorted: bool,
+        derefs: usize,
+    },
+    /// #[debug(max_len = N)]: at most N characters of a string
+    MaxLen(usize),
+    /// #[debug(with = "path")]: a `fn(&T, &mut fmt::Formatter) -> fmt::Result`
+    With(Path),
+    /// #[debug(hex)], #[debug(hexdump)] or #[debug(base64)] for byte buffers
+    Bytes(Encoding),
+    /// #[debug(flatten)]: the fields of a nested DebugFields value, inlined into this one
+    Flatten,
+}
+
+/// The #[debug ...] attributes of a field
+struct FieldAttrs {
+    style: Style,
+    /// Set by #[debug(bound = "...")]: replaces the bounds inferred from this field
+    bound: Option<Vec<WherePredicate>>,
+    /// Set by #[debug(rename = "...")]: the name printed instead of the field's
+    rename: Option<LitStr>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field, container: &ContainerAttrs) -> Result<Self> {
+        let mut ty = &field.ty;
+        let mut derefs = 0;
+        while let Type::Reference(reference) = ty {
+            ty = &reference.elem;
+            derefs += 1;
+        }
+        let collection = Collection::of(ty);
+        let mut style = None;
+        let mut bound = None;
+        let mut max_items = None;
+        let mut sorted = None;
+        let mut rename = None;
+        for meta in attr_metas(&field.attrs, "debug")? {
+            let (new_style, span) = match meta {
+                AttrMeta::Format(lit) => {
+                    let parsed = FormatString::parse(&lit)?;
+                    parsed.expect_single_value()?;
+                    (Style::Format(parsed), lit.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("bound") => {
+                    bound = Some(parse_bound(&lit)?);
+                    continue;
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("rename") => {
+                    if field.ident.is_none() {
+                        return Err(Error::new_spanned(path, "only named fields can be renamed"));
+                    }
+                    rename = Some(lit);
+                    continue;
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path)))
+                    if path.is_ident("flatten") =>
+                {
+                    if field.ident.is_none() {
+                        return Err(Error::new_spanned(
+                            path,
+                            "only named fields can be flattened",
+                        ));
+                    }
+                    // The inlined fields are only known at runtime, so they can't be sorted
+                    // among the others.
+                    if container.alphabetical {
+                        return Err(Error::new_spanned(
+                            path,
+                            "`flatten` cannot be combined with `order = \"alphabetical\"`",
+                        ));
+                    }
+                    (Style::Flatten, path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("skip") => {
+                    (Style::Skip, path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("hex") => {
+                    (Style::Bytes(Encoding::Hex), path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path)))
+                    if path.is_ident("hexdump") =>
+                {
+                    (Style::Bytes(Encoding::HexDump), path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("base64") => {
+                    (Style::Bytes(Encoding::Base64), path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("redact") => {
+                    (Style::Redact { len: false }, path.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::List(list)))
+                    if list.path.is_ident("redact") =>
+                {
+                    match list.nested.iter().collect::<Vec<_>>().as_slice() {
+                        [NestedMeta::Meta(Meta::Path(path))] if path.is_ident("len") => {}
+                        _ => return Err(Error::new_spanned(list, "expected `redact(len)`")),
+                    }
+                    (Style::Redact { len: true }, list.span())
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit,
+                    ..
+                }))) if path.is_ident("max_items") => {
+                    max_items = Some((parse_limit(&lit)?, path.span()));
+                    continue;
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("sorted") => {
+                    sorted = Some(path.span());
+                    continue;
+                }
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit,
+                    ..
+                }))) if path.is_ident("with") => match lit {
+                    Lit::Str(lit) => (Style::With(lit.parse()?), lit.span()),
+                    lit => return Err(Error::new_spanned(lit, "expected a path in a string")),
+                },
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit,
+                    ..
+                }))) if path.is_ident("max_len") => {
+                    (Style::MaxLen(parse_limit(&lit)?), path.span())
+                }
+                AttrMeta::Nested(meta) => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected `debug = \"...\"` or one of `debug(bound = \"...\")`, \
+                         `debug(skip)`, `debug(redact)`, `debug(max_items = N)`, \
+                         `debug(max_len = N)`, `debug(sorted)`, `debug(with = \"...\")`, \
+                         `debug(hex)`, `debug(hexdump)`, `debug(base64)`, \
+                         `debug(rename = \"...\")`, `debug(flatten)`",
+                    ))
+                }
+            };
+            if style.is_some() {
+                return Err(Error::new(
+                    span,
+                    "conflicting debug attributes on this field",
+                ));
+            }
+            style = Some(new_style);
+        }
+        if let Some(span) = max_items.map(|(_, span)| span).or(sorted) {
+            if style.is_some() {
+                return Err(Error::new(
+                    span,
+                    "conflicting debug attributes on this field",
+                ));
+            }
+            style = Some(Style::Items {
+                kind: collection.unwrap_or(Collection::List),
+                limit: max_items.map(|(limit, _)| limit),
+                sorted: sorted.is_some(),
+                derefs,
+            });
+        }
+        Ok(FieldAttrs {
+            style: style
+                .or_else(|| {
+                    Some(Style::Items {
+                        kind: collection?,
+                        limit: Some(container.max_items?),
+                        sorted: false,
+                        derefs,
+                    })
+                })
+                .unwrap_or(Style::Debug),
+            bound,
+            rename,
+        })
+    }
+
+    /// The traits needed to format `field`, with the types that need them, e.g. the field's
+    /// type and `std::fmt::Debug`
+    fn traits<'a>(&self, field: &'a Field) -> Vec<(&'a Type, Path)> {
+        let ty = &field.ty;
+        match &self.style {
+            Style::Debug => vec![(ty, parse_quote!(std::fmt::Debug))],
+            Style::Flatten => vec![(ty, parse_quote!(::derive_debug::DebugFields))],
+            Style::Format(format) => vec![(ty, format.trait_path())],
+            Style::Items { sorted, .. } => {
+                let mut traits = vec![(ty, parse_quote!(std::fmt::Debug))];
+                if *sorted {
+                    let item = item_type(ty).unwrap_or(ty);
+                    traits.push((item, parse_quote!(std::cmp::Ord)));
+                }
+                traits
+            }
+            Style::Skip
+            | Style::Redact { .. }
+            | Style::MaxLen(_)
+            | Style::With(_)
+            | Style::Bytes(_) => Vec::new(),
+        }
+    }
+
+    /// An expression of a type implementing Debug that renders the field bound to `binding`
+    fn value(&self, binding: &Ident) -> proc_macro2::TokenStream {
+        match &self.style {
+            Style::Debug | Style::Skip | Style::Flatten => quote!(#binding),
+            Style::Format(FormatString { lit, .. }) => quote!(&format_args!(#lit, #binding)),
+            Style::Redact { len: false } => quote!(&format_args!("<redacted>")),
+            Style::Redact { len: true } => {
+                quote!(&format_args!("<redacted len={}>", #binding.len()))
+            }
+            Style::Items {
+                kind,
+                limit,
+                sorted,
+                derefs,
+            } => {
+                let helper = Helper::Items {
+                    kind: *kind,
+                    sorted: *sorted,
+                }
+                .ident();
+                let derefs = (0..*derefs).map(|_| quote!(*));
+                let limit = match limit {
+                    Some(limit) => quote!(#limit),
+                    None => quote!(usize::MAX),
+                };
+                quote!(&#helper { collection: #(#derefs)* #binding, limit: #limit })
+            }
+            Style::MaxLen(limit) => {
+                let helper = Helper::Str.ident();
+                quote! {
+                    &#helper {
+                        string: std::convert::AsRef::<str>::as_ref(#binding),
+                        limit: #limit,
+                    }
+                }
+            }
+            Style::With(path) => {
+                let helper = Helper::With.ident();
+                quote!(&#helper { value: #binding, fmt: #path })
+            }
+            Style::Bytes(encoding) => {
+                let helper = Helper::Bytes(*encoding).ident();
+                quote!(&#helper { bytes: std::convert::AsRef::<[u8]>::as_ref(#binding) })
+            }
+        }
+    }
+
+    /// The adapter used by `value`, if any
+    fn helper(&self) -> Option<Helper> {
+        match &self.style {
+            Style::Items { kind, sorted, .. } => Some(Helper::Items {
+                kind: *kind,
+                sorted: *sorted,
+            }),
+            Style::MaxLen(_) => Some(Helper::Str),
+            Style::With(_) => Some(Helper::With),
+            Style::Bytes(encoding) => Some(Helper::Bytes(*encoding)),
+            _ => None,
+        }
+    }
+}
+
+/// The #[debug(...)] attributes of an enum variant
+struct VariantAttrs {
+    /// Set by #[debug(rename = "...")]: the name printed instead of the variant's
+    rename: Option<LitStr>,
+}
+
+impl VariantAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut rename = None;
+        for meta in attr_metas(attrs, "debug")? {
+            match meta {
+                AttrMeta::Nested(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                }))) if path.is_ident("rename") => {
+                    rename = Some(lit);
+                }
+                AttrMeta::Format(lit) => {
+                    return Err(Error::new_spanned(
+                        lit,
+                        "expected `debug(rename = \"...\")`",
+                    ))
+                }
+                AttrMeta::Nested(meta) => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "expected `debug(rename = \"...\")`",
+                    ))
+                }
+            }
+        }
+        Ok(VariantAttrs { rename })
+    }
+}
+
+/// One arm of the generated `match self`: a struct, or one variant of an enum
+struct Arm<'a> {
+    /// The path in the arm's pattern, e.g. `Self` or `Self::Variant`
+    path: proc_macro2::TokenStream,
+    /// The name printed for the struct or variant
+    name: String,
+    fields: &'a Fields,
+    /// The attributes of `fields`, in the same order
+    attrs: Vec<FieldAttrs>,
+    /// Whether named fields are printed sorted by name
+    alphabetical: bool,
+}
+
+impl<'a> Arm<'a> {
+    fn parse(
+        path: proc_macro2::TokenStream,
+        name: String,
+        fields: &'a Fields,
+        container: &ContainerAttrs,
+    ) -> Result<Self> {
+        let attrs = fields
+            .iter()
+            .map(|f| FieldAttrs::parse(f, container))
+            .collect::<Result<_>>()?;
+        Ok(Arm {
+            path,
+            name,
+            fields,
+            attrs,
+            alphabetical: container.alphabetical,
+        })
+    }
+
+    /// The pattern binding the fields to `__self_0`, `__self_1`, ... or with another prefix
+    fn pattern(&self, prefix: &str) -> proc_macro2::TokenStream {
+        let path = &self.path;
+        let bindings = (0..self.fields.len()).map(|i| format_ident!("{}{}", prefix, i));
+        match self.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident);
+                quote!(#path { #(#names: #bindings),* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#bindings),*)),
+            Fields::Unit => quote!(#path),
+        }
+    }
+
+    /// The fields that are printed, in order, with their printed names (the index for tuple
+    /// fields) and indices
+    fn shown(&self) -> Vec<(String, usize, &FieldAttrs)> {
+        let mut shown = self
+            .fields
+            .iter()
+            .zip(&self.attrs)
+            .enumerate()
+            .filter(|(_, (_, attrs))| !matches!(attrs.style, Style::Skip))
+            .map(|(i, (field, attrs))| {
+                let name = match (&attrs.rename, &field.ident) {
+                    (Some(rename), _) => rename.value(),
+                    (None, Some(ident)) => ident.unraw().to_string(),
+                    (None, None) => i.to_string(),
+                };
+                (name, i, attrs)
+            })
+            .collect::<Vec<_>>();
+        if self.alphabetical && matches!(self.fields, Fields::Named(_)) {
+            shown.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        shown
+    }
+
+    fn skips(&self) -> bool {
+        self.attrs.iter().any(|a| matches!(a.style, Style::Skip))
+    }
+
+    fn flattens(&self) -> bool {
+        self.attrs.iter().any(|a| matches!(a.style, Style::Flatten))
+    }
+
+    /// Statements adding the named fields to `__debug: &mut DebugStruct`, and setting
+    /// `__skipped` if fields were left out
+    fn field_statements(&self) -> Vec<proc_macro2::TokenStream> {
+        self.shown()
+            .into_iter()
+            .map(|(name, i, attrs)| {
+                let binding = format_ident!("__self_{}", i);
+                match attrs.style {
+                    Style::Flatten => quote! {
+                        __skipped |= ::derive_debug::DebugFields::__debug_struct(#binding, __debug);
+                    },
+                    _ => {
+                        let value = attrs.value(&binding);
+                        quote!(__debug.field(#name, #value);)
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// `pattern => body,`
+    fn expand(&self) -> proc_macro2::TokenStream {
+        let pattern = self.pattern("__self_");
+        let name = &self.name;
+        let finish = if self.skips() {
+            quote!(finish_non_exhaustive)
+        } else {
+            quote!(finish)
+        };
+        let shown = self.shown();
+        let values = shown
+            .iter()
+            .map(|(_, i, attrs)| attrs.value(&format_ident!("__self_{}", i)));
+        match self.fields {
+            // Whether the output is non-exhaustive is only known at runtime.
+            Fields::Named(_) if self.flattens() => {
+                let skipped = self.skips();
+                let statements = self.field_statements();
+                quote! {
+                    #pattern => {
+                        let __debug = &mut f.debug_struct(#name);
+                        let mut __skipped = #skipped;
+                        #(#statements)*
+                        if __skipped {
+                            __debug.finish_non_exhaustive()
+                        } else {
+                            __debug.finish()
+                        }
+                    }
+                }
+            }
+            Fields::Named(_) => {
+                let names = shown.iter().map(|(name, ..)| name);
+                quote! {
+                    #pattern => f
+                        .debug_struct(#name)
+                        #(.field(#names, #values))*
+                        .#finish(),
+                }
+            }
+            Fields::Unnamed(_) => quote! {
+                #pattern => f
+                    .debug_tuple(#name)
+                    #(.field(#values))*
+                    .#finish(),
+            },
+            Fields::Unit => quote! {
+                #pattern => f.write_str(#name),
+            },
+        }
+    }
+
+    /// `pattern => body,` for `DebugFields::debug_fields`
+    fn expand_visit(&self) -> proc_macro2::TokenStream {
+        let pattern = self.pattern("__self_");
+        let statements = self.shown().into_iter().map(|(name, i, attrs)| {
+            let binding = format_ident!("__self_{}", i);
+            match attrs.style {
+                Style::Flatten => quote! {
+                    ::derive_debug::DebugFields::debug_fields(#binding, visit);
+                },
+                _ => {
+                    let value = attrs.value(&binding);
+                    quote!(visit(#name, #value);)
+                }
+            }
+        });
+        quote! {
+            #pattern => {
+                #(#statements)*
+            }
+        }
+    }
+
+    /// `pattern => body,` for `DebugFields::__debug_struct`, which is used to flatten a
+    /// value into another
+    fn expand_debug_struct(&self) -> proc_macro2::TokenStream {
+        let pattern = self.pattern("__self_");
+        let skipped = self.skips();
+        let mutability = self.flattens().then(|| quote!(mut));
+        let statements = self.field_statements();
+        quote! {
+            #pattern => {
+                let #mutability __skipped = #skipped;
+                #(#statements)*
+                __skipped
+            }
+        }
+    }
+
+    /// `(self pattern, other pattern) => body,` for `DebugDiff::debug_diff`, comparing the
+    /// fields of two values of the same variant
+    fn expand_diff(&self) -> proc_macro2::TokenStream {
+        let pattern = self.pattern("__self_");
+        let other = self.pattern("__other_");
+        let statements = self.shown().into_iter().map(|(name, i, attrs)| {
+            let left = format_ident!("__self_{}", i);
+            let right = format_ident!("__other_{}", i);
+            match attrs.style {
+                Style::Flatten => quote! {
+                    (&&::derive_debug::__private::FlattenDiff(#left, #right))
+                        .__diff("", &mut __diffs);
+                },
+                _ => {
+                    let left = attrs.value(&left);
+                    let right = attrs.value(&right);
+                    quote! {
+                        (&&::derive_debug::__private::Diff(#left, #right))
+                            .__diff(#name, &mut __diffs);
+                    }
+                }
+            }
+        });
+        quote! {
+            (#pattern, #other) => {
+                #(#statements)*
+            }
+        }
+    }
+}
+
+#[proc_macro_derive(CustomDebug, attributes(debug))]
+pub fn derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CustomDisplay, attributes(display))]
+pub fn derive_display(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    display::expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let type_name = &input.ident;
+    let container = ContainerAttrs::parse(&input.attrs)?;
+    let arms = match &input.data {
+        Data::Struct(data) => vec![Arm::parse(
+            quote!(Self),
+            type_name.to_string(),
+            &data.fields,
+            &container,
+        )?],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|v| {
+                let variant = &v.ident;
+                let name = match VariantAttrs::parse(&v.attrs)?.rename {
+                    Some(rename) => rename.value(),
+                    None => variant.to_string(),
+                };
+                Arm::parse(quote!(Self::#variant), name, &v.fields, &container)
+            })
+            .collect::<Result<_>>()?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input,
+                "CustomDebug does not support unions",
+            ))
+        }
+    };
+
+    // impl std::fmt::Debug for Field {
+    //     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    //         match self {
+    //             Self {
+    //                 name: __self_0,
+    //                 bitmask: __self_1,
+    //             } => f
+    //                 .debug_struct("Field")
+    //                 .field("name", __self_0)
+    //                 .field("bitmask", &format_args!("0b{:08b}", __self_1))
+    //                 .finish(),
+    //         }
+    //     }
+    // }
+    //
+    // Tuple and unit structs are matched as `Self(__self_0, ..)` and `Self`, and for enums
+    // there is one arm per variant, e.g.
+    //
+    //             Self::Io(__self_0) => f.debug_tuple("Io").field(__self_0).finish(),
+    //             Self::Eof => f.write_str("Eof"),
+    let body = if arms.is_empty() {
+        quote!(match *self {})
+    } else {
+        let arms = arms.iter().map(Arm::expand);
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    };
+    let fields = arms
+        .iter()
+        .flat_map(|arm| arm.fields.iter().zip(&arm.attrs));
+
+    // With #[debug(depth_limit = N)] or #[debug(detect_cycles)], the addresses of the
+    // values of this type being formatted are kept on a stack, so that recursion through
+    // pointers can be cut short. The stack is shared by all instantiations of a generic
+    // type, and a value at offset 0 of another has the same address, so each address is
+    // paired with the name of the instantiation:
+    //
+    // thread_local! {
+    //     static __DEBUG_STACK: std::cell::RefCell<Vec<(usize, &'static str)>> = ...;
+    // }
+    // struct __DebugGuard; // pops the stack when dropped, even on panic
+    //
+    // let __value = (self as *const Self as *const () as usize, type_name::<Self>());
+    // if __DEBUG_STACK.with(|stack| stack.borrow().contains(&__value)) {
+    //     return f.write_str("<cycle>");
+    // }
+    // if __DEBUG_STACK.with(|stack| stack.borrow().len()) >= 4 {
+    //     return f.write_str("...");
+    // }
+    // __DEBUG_STACK.with(|stack| stack.borrow_mut().push(__value));
+    // let __guard = __DebugGuard;
+    // match self { ... }
+    let body = if container.depth_limit.is_some() || container.detect_cycles {
+        let cycle_check = container.detect_cycles.then(|| {
+            quote! {
+                if __DEBUG_STACK.with(|stack| stack.borrow().contains(&__value)) {
+                    return f.write_str("<cycle>");
+                }
+            }
+        });
+        let depth_check = container.depth_limit.map(|limit| {
+            quote! {
+                if __DEBUG_STACK.with(|stack| stack.borrow().len()) >= #limit {
+                    return f.write_str("...");
+                }
+            }
+        });
+        quote! {
+            std::thread_local! {
+                static __DEBUG_STACK: std::cell::RefCell<std::vec::Vec<(usize, &'static str)>> =
+                    std::cell::RefCell::new(std::vec::Vec::new());
+            }
+
+            struct __DebugGuard;
+
+            impl std::ops::Drop for __DebugGuard {
+                fn drop(&mut self) {
+                    __DEBUG_STACK.with(|stack| stack.borrow_mut().pop());
+                }
+            }
+
+            let __value = (
+                self as *const Self as *const () as usize,
+                std::any::type_name::<Self>(),
+            );
+            #cycle_check
+            #depth_check
+            __DEBUG_STACK.with(|stack| stack.borrow_mut().push(__value));
+            let __guard = __DebugGuard;
+            #body
+        }
+    } else {
+        body
+    };
+
+    // Adapters used by the fields, e.g. for #[debug(max_items = N)], see helper.rs
+    let mut helpers = fields
+        .clone()
+        .filter_map(|(_, attrs)| attrs.helper())
+        .collect::<Vec<_>>();
+    helpers.sort();
+    helpers.dedup();
+    let helpers = helpers.into_iter().map(Helper::expand).collect::<Vec<_>>();
+
+    // where T: std::fmt::Debug, T::Value: std::fmt::Debug, ...
+    //
+    // unless replaced by #[debug(bound = "...")] on the type or on individual fields
+    let mut generics = input.generics.clone();
+    let bounds = match container.bound {
+        Some(bound) => bound,
+        None => {
+            let inferred = bound::infer(
+                &input.generics,
+                fields
+                    .clone()
+                    .filter(|(_, attrs)| attrs.bound.is_none())
+                    .flat_map(|(f, attrs)| attrs.traits(f)),
+            );
+            let explicit = fields.filter_map(|(_, attrs)| attrs.bound.clone());
+            inferred.into_iter().chain(explicit.flatten()).collect()
+        }
+    };
+    generics.make_where_clause().predicates.extend(bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // The same fields, for structured logging and #[debug(flatten)]:
+    //
+    // impl ::derive_debug::DebugFields for Field {
+    //     fn debug_fields(&self, visit: &mut dyn FnMut(&str, &dyn std::fmt::Debug)) {
+    //         match self {
+    //             Self { name: __self_0, bitmask: __self_1 } => {
+    //                 visit("name", __self_0);
+    //                 visit("bitmask", &format_args!("0b{:08b}", __self_1));
+    //             }
+    //         }
+    //     }
+    //
+    //     fn __debug_struct(&self, __debug: &mut std::fmt::DebugStruct) -> bool {
+    //         match self {
+    //             Self { name: __self_0, bitmask: __self_1 } => {
+    //                 let __skipped = false;
+    //                 __debug.field("name", __self_0);
+    //                 __debug.field("bitmask", &format_args!("0b{:08b}", __self_1));
+    //                 __skipped
+    //             }
+    //         }
+    //     }
+    // }
+    let match_arms = |arms: Vec<proc_macro2::TokenStream>| {
+        if arms.is_empty() {
+            quote!(match *self {})
+        } else {
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+    let visit_body = match_arms(arms.iter().map(Arm::expand_visit).collect());
+    let debug_struct_body = match_arms(arms.iter().map(Arm::expand_debug_struct).collect());
+
+    // The fields that print differently, recursing into those that implement DebugDiff
+    // themselves, see __private in the derive_debug crate:
+    //
+    // impl ::derive_debug::DebugDiff for Field {
+    //     fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)> {
+    //         use ::derive_debug::__private::{Leaf as _, Nested as _};
+    //         let mut __diffs = Vec::new();
+    //         match (self, other) {
+    //             (
+    //                 Self { name: __self_0, bitmask: __self_1 },
+    //                 Self { name: __other_0, bitmask: __other_1 },
+    //             ) => {
+    //                 (&&::derive_debug::__private::Diff(__self_0, __other_0))
+    //                     .__diff("name", &mut __diffs);
+    //                 (&&::derive_debug::__private::Diff(
+    //                     &format_args!("0b{:08b}", __self_1),
+    //                     &format_args!("0b{:08b}", __other_1),
+    //                 ))
+    //                 .__diff("bitmask", &mut __diffs);
+    //             }
+    //         }
+    //         __diffs
+    //     }
+    // }
+    //
+    // with a last arm for enums with several variants, reporting different variants as a
+    // difference of the whole values.
+    let diff_body = if arms.is_empty() {
+        quote!(match *self {})
+    } else {
+        let diff_arms = arms.iter().map(Arm::expand_diff);
+        let other_variants = (arms.len() > 1).then(|| {
+            quote! {
+                _ => __diffs.push((
+                    std::string::String::new(),
+                    std::format!("{:?}", self),
+                    std::format!("{:?}", other),
+                )),
+            }
+        });
+        quote! {
+            let mut __diffs = std::vec::Vec::new();
+            match (self, other) {
+                #(#diff_arms)*
+                #other_variants
+            }
+            __diffs
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics std::fmt::Debug for #type_name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                #(#helpers)*
+                #body
+            }
+        }
+
+        impl #impl_generics ::derive_debug::DebugFields for #type_name #ty_generics #where_clause {
+            fn debug_fields(&self, visit: &mut dyn FnMut(&str, &dyn std::fmt::Debug)) {
+                #(#helpers)*
+                #visit_body
+            }
+
+            fn __debug_struct(&self, __debug: &mut std::fmt::DebugStruct) -> bool {
+                #(#helpers)*
+                #debug_struct_body
+            }
+        }
+
+        impl #impl_generics ::derive_debug::DebugDiff for #type_name #ty_generics #where_clause {
+            fn debug_diff(
+                &self,
+                other: &Self,
+            ) -> std::vec::Vec<(std::string::String, std::string::String, std::string::String)> {
+                use ::derive_debug::__private::{Leaf as _, Nested as _};
+                #(#helpers)*
+                #diff_body
+            }
+        }
+    })
+}
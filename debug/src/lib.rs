@@ -1,8 +1,186 @@
-use proc_macro::TokenStream;
+// Crates that have the "proc-macro" crate type are only allowed to export
+// procedural macros, so the derives live in the derive_debug_impl crate and are
+// re-exported from here, alongside the DebugFields and DebugDiff traits that
+// CustomDebug implements.
+use std::fmt::{Debug, DebugStruct};
+use std::rc::Rc;
+use std::sync::Arc;
 
-#[proc_macro_derive(CustomDebug)]
-pub fn derive(input: TokenStream) -> TokenStream {
-    let _ = input;
+pub use derive_debug_impl::{CustomDebug, CustomDisplay};
 
-    unimplemented!()
+/// Structured access to the fields of a value, as printed by its `Debug` impl.
+///
+/// `#[derive(CustomDebug)]` implements it following the same `#[debug]` attributes: fields
+/// are renamed, reordered, formatted, redacted, skipped and flattened the same way. Tuple
+/// fields are named by their index, and for enums the fields of the current variant are
+/// visited.
+///
+/// ```
+/// use derive_debug::{CustomDebug, DebugFields};
+///
+/// #[derive(CustomDebug)]
+/// pub struct Request {
+///     method: &'static str,
+///     #[debug = "{:#x}"]
+///     flags: u8,
+/// }
+///
+/// let request = Request { method: "GET", flags: 0x10 };
+/// let mut pairs = Vec::new();
+/// request.debug_fields(&mut |name, value| pairs.push(format!("{}={:?}", name, value)));
+/// assert_eq!(pairs, ["method=\"GET\"", "flags=0x10"]);
+/// ```
+pub trait DebugFields {
+    /// Calls `visit` with the name and value of each field, in the order they are printed.
+    fn debug_fields(&self, visit: &mut dyn FnMut(&str, &dyn Debug));
+
+    /// Adds the fields to `debug`, returning whether any were left out so the output can be
+    /// marked non-exhaustive. Used by #[debug(flatten)].
+    #[doc(hidden)]
+    fn __debug_struct(&self, debug: &mut DebugStruct) -> bool {
+        self.debug_fields(&mut |name, value| {
+            debug.field(name, value);
+        });
+        false
+    }
+}
+
+/// Field-by-field comparison of two values, as printed by their `Debug` impls.
+///
+/// `#[derive(CustomDebug)]` implements it with each field formatted as configured by its
+/// `#[debug]` attributes, recursing into fields whose type implements `DebugDiff` as well,
+/// including through references, `Box`, `Rc` and `Arc`. Fields whose type depends on a type
+/// parameter are compared as a whole.
+///
+/// ```
+/// use derive_debug::{CustomDebug, DebugDiff};
+///
+/// #[derive(CustomDebug)]
+/// pub struct Config {
+///     name: &'static str,
+///     limits: Limits,
+/// }
+///
+/// #[derive(CustomDebug)]
+/// pub struct Limits {
+///     #[debug = "{} MiB"]
+///     memory: u32,
+///     cpus: u32,
+/// }
+///
+/// let left = Config { name: "prod", limits: Limits { memory: 512, cpus: 2 } };
+/// let right = Config { name: "prod", limits: Limits { memory: 1024, cpus: 2 } };
+/// assert_eq!(
+///     left.debug_diff(&right),
+///     [("limits.memory".to_owned(), "512 MiB".to_owned(), "1024 MiB".to_owned())],
+/// );
+/// ```
+pub trait DebugDiff {
+    /// The fields that print differently, as `(path, left, right)`. The paths of nested
+    /// fields are joined with `.`, and an empty path stands for the whole value, e.g. when
+    /// two enums hold different variants.
+    fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)>;
+}
+
+impl<T: DebugDiff + ?Sized> DebugDiff for &T {
+    fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)> {
+        (**self).debug_diff(other)
+    }
+}
+
+impl<T: DebugDiff + ?Sized> DebugDiff for Box<T> {
+    fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)> {
+        (**self).debug_diff(other)
+    }
+}
+
+impl<T: DebugDiff + ?Sized> DebugDiff for Rc<T> {
+    fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)> {
+        (**self).debug_diff(other)
+    }
+}
+
+impl<T: DebugDiff + ?Sized> DebugDiff for Arc<T> {
+    fn debug_diff(&self, other: &Self) -> Vec<(String, String, String)> {
+        (**self).debug_diff(other)
+    }
+}
+
+// Support for the code generated by CustomDebug. Fields are compared through
+//
+//     (&&Diff(left, right)).__diff(path, &mut diffs)
+//
+// which, by the rules of method resolution, picks `Nested` when the type of the field
+// implements DebugDiff and falls back to `Leaf` otherwise.
+#[doc(hidden)]
+pub mod __private {
+    use super::{DebugDiff, DebugFields};
+    use std::fmt::Debug;
+
+    type Diffs = Vec<(String, String, String)>;
+
+    fn join(path: &str, field: String) -> String {
+        match (path, field.as_str()) {
+            (path, "") => path.to_owned(),
+            ("", _) => field,
+            (path, field) => format!("{}.{}", path, field),
+        }
+    }
+
+    /// A field of both values
+    pub struct Diff<'a, T: ?Sized>(pub &'a T, pub &'a T);
+
+    /// A #[debug(flatten)] field of both values, whose fields are compared as if they were
+    /// fields of the parent
+    pub struct FlattenDiff<'a, T: ?Sized>(pub &'a T, pub &'a T);
+
+    pub trait Nested {
+        fn __diff(&self, path: &str, diffs: &mut Diffs);
+    }
+
+    pub trait Leaf {
+        fn __diff(&self, path: &str, diffs: &mut Diffs);
+    }
+
+    impl<T: DebugDiff + ?Sized> Nested for &Diff<'_, T> {
+        fn __diff(&self, path: &str, diffs: &mut Diffs) {
+            for (field, left, right) in self.0.debug_diff(self.1) {
+                diffs.push((join(path, field), left, right));
+            }
+        }
+    }
+
+    impl<T: Debug + ?Sized> Leaf for Diff<'_, T> {
+        fn __diff(&self, path: &str, diffs: &mut Diffs) {
+            let left = format!("{:?}", self.0);
+            let right = format!("{:?}", self.1);
+            if left != right {
+                diffs.push((path.to_owned(), left, right));
+            }
+        }
+    }
+
+    impl<T: DebugDiff + ?Sized> Nested for &FlattenDiff<'_, T> {
+        fn __diff(&self, path: &str, diffs: &mut Diffs) {
+            Nested::__diff(&&Diff(self.0, self.1), path, diffs);
+        }
+    }
+
+    // Without DebugDiff, the visited fields are compared in order.
+    impl<T: DebugFields + ?Sized> Leaf for FlattenDiff<'_, T> {
+        fn __diff(&self, path: &str, diffs: &mut Diffs) {
+            let fields = |value: &T| {
+                let mut fields = Vec::new();
+                value.debug_fields(&mut |name, value| {
+                    fields.push((name.to_owned(), format!("{:?}", value)));
+                });
+                fields
+            };
+            for ((field, left), (_, right)) in fields(self.0).into_iter().zip(fields(self.1)) {
+                if left != right {
+                    diffs.push((join(path, field), left, right));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,41 @@
+// Logs compare the output of CustomDebug with that of the standard library's
+// derive, so the two must match byte for byte, including the pretty-printed
+// `{:#?}` form and nested values.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Field {
+    name: &'static str,
+    bitmask: u8,
+    tags: Vec<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct StdField {
+    name: &'static str,
+    bitmask: u8,
+    tags: Vec<&'static str>,
+}
+
+fn main() {
+    let custom = Field {
+        name: "F",
+        bitmask: 0b00011100,
+        tags: vec!["a", "b"],
+    };
+    let std = StdField {
+        name: "F",
+        bitmask: 0b00011100,
+        tags: vec!["a", "b"],
+    };
+
+    assert_eq!(
+        format!("{:?}", custom),
+        format!("{:?}", std).replace("StdField", "Field"),
+    );
+    assert_eq!(
+        format!("{:#?}", custom),
+        format!("{:#?}", std).replace("StdField", "Field"),
+    );
+}
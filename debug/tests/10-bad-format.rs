@@ -0,0 +1,32 @@
+// Mistakes in a #[debug = "..."] format string are reported on the attribute
+// rather than as an error from inside the generated format_args! call. Where
+// the compiler supports subspans, the error points at the offending
+// placeholder within the literal.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct TwoPlaceholders {
+    #[debug = "{:x}..{:x}"]
+    range: u8,
+}
+
+#[derive(CustomDebug)]
+pub struct NoPlaceholder {
+    #[debug = "bitmask"]
+    bitmask: u8,
+}
+
+#[derive(CustomDebug)]
+pub struct UnsupportedSpec {
+    #[debug = "0b{:08z}"]
+    bitmask: u8,
+}
+
+#[derive(CustomDebug)]
+pub struct Unmatched {
+    #[debug = "{:?"]
+    bitmask: u8,
+}
+
+fn main() {}
@@ -0,0 +1,64 @@
+// Bounds are only added for type parameters that are actually formatted, and
+// only for the trait that formats them: a field with #[debug = "{:x}"] needs
+// LowerHex, not Debug. Parameters used only inside PhantomData need no bound,
+// and associated types get a bound of their own, including in the qualified
+// `<T as Trait>::Value` form.
+
+use derive_debug::CustomDebug;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+pub trait Trait {
+    type Value;
+}
+
+// Implements LowerHex but not Debug.
+pub struct Hex(u8);
+
+impl fmt::LowerHex for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+#[derive(CustomDebug)]
+pub struct Wrapper<T: Trait, H, M> {
+    values: Vec<T::Value>,
+    #[debug = "{:#x}"]
+    hex: H,
+    marker: PhantomData<(T, M)>,
+}
+
+#[derive(CustomDebug)]
+pub struct Qualified<T: Trait> {
+    value: <T as Trait>::Value,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    struct Id;
+    struct NotDebug;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Wrapper<Id, Hex, NotDebug>>();
+
+    impl Trait for NotDebug {
+        type Value = u8;
+    }
+
+    assert_debug::<Qualified<NotDebug>>();
+    let qualified: Qualified<NotDebug> = Qualified { value: 7 };
+    assert_eq!(format!("{:?}", qualified), "Qualified { value: 7 }");
+
+    let wrapper: Wrapper<Id, Hex, NotDebug> = Wrapper {
+        values: vec![1, 2],
+        hex: Hex(255),
+        marker: PhantomData,
+    };
+    let debug = format!("{:?}", wrapper);
+    assert!(debug.starts_with("Wrapper { values: [1, 2], hex: 0xff, marker: PhantomData<"));
+}
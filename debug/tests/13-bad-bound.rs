@@ -0,0 +1,18 @@
+// A `bound` that does not parse as where-predicates is reported on the
+// attribute.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(bound = "T: Debug;")]
+pub struct Wrapper<T> {
+    value: T,
+}
+
+#[derive(CustomDebug)]
+pub struct Field<T> {
+    #[debug(bound = "T Debug")]
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,42 @@
+// Enums are formatted like the standard library's derive does: unit variants
+// as their name, tuple variants with debug_tuple and struct variants with
+// debug_struct. Field attributes work inside variants, and a variant-level
+// #[debug(rename = "...")] changes the name that is printed.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub enum Error {
+    Eof,
+    Io(std::io::ErrorKind),
+    Parse {
+        line: usize,
+        #[debug = "{:#x}"]
+        byte: u8,
+    },
+    #[debug(rename = "Other")]
+    Unknown(&'static str, #[debug = "code {}"] i32),
+}
+
+#[derive(CustomDebug)]
+pub enum Never {}
+
+fn main() {
+    assert_eq!(format!("{:?}", Error::Eof), "Eof");
+    assert_eq!(
+        format!("{:?}", Error::Io(std::io::ErrorKind::NotFound)),
+        "Io(NotFound)",
+    );
+    assert_eq!(
+        format!("{:?}", Error::Parse { line: 3, byte: 255 }),
+        "Parse { line: 3, byte: 0xff }",
+    );
+    assert_eq!(
+        format!("{:?}", Error::Unknown("boom", 7)),
+        r#"Other("boom", code 7)"#,
+    );
+    assert_eq!(
+        format!("{:#?}", Error::Parse { line: 3, byte: 255 }),
+        "Parse {\n    line: 3,\n    byte: 0xff,\n}",
+    );
+}
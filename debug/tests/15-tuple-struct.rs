@@ -0,0 +1,34 @@
+// Tuple structs are formatted with debug_tuple and unit structs as their bare
+// name, matching the standard library's derive. Field attributes apply to
+// tuple fields too, which is handy for newtype IDs.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct UserId(#[debug = "{:#x}"] u64);
+
+#[derive(CustomDebug)]
+pub struct Pair<T>(T, &'static str);
+
+#[derive(Debug)]
+pub struct StdPair<T>(T, &'static str);
+
+#[derive(CustomDebug)]
+pub struct Marker;
+
+fn main() {
+    assert_eq!(format!("{:?}", UserId(255)), "UserId(0xff)");
+    assert_eq!(format!("{:?}", Marker), "Marker");
+    assert_eq!(format!("{:#?}", Marker), "Marker");
+
+    let pair = Pair(vec![1, 2], "x");
+    let std = StdPair(vec![1, 2], "x");
+    assert_eq!(
+        format!("{:?}", pair),
+        format!("{:?}", std).replace("StdPair", "Pair"),
+    );
+    assert_eq!(
+        format!("{:#?}", pair),
+        format!("{:#?}", std).replace("StdPair", "Pair"),
+    );
+}
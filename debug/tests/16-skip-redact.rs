@@ -0,0 +1,62 @@
+// Fields holding secrets can be left out with #[debug(skip)], which marks the
+// output as non-exhaustive, or replaced by a fixed placeholder with
+// #[debug(redact)]. #[debug(redact(len))] keeps the length, which is often
+// useful when diagnosing truncated tokens. Neither needs the field's type to
+// implement Debug.
+
+use derive_debug::CustomDebug;
+
+pub struct Secret(String);
+
+#[derive(CustomDebug)]
+pub struct Credentials {
+    user: String,
+    #[debug(redact)]
+    password: String,
+    #[debug(redact(len))]
+    token: Vec<u8>,
+    #[debug(skip)]
+    key: Secret,
+}
+
+#[derive(CustomDebug)]
+pub struct Session(u32, #[debug(skip)] Secret);
+
+#[derive(CustomDebug)]
+pub enum Auth {
+    Basic {
+        user: String,
+        #[debug(redact)]
+        password: String,
+    },
+    Key(#[debug(skip)] Secret),
+}
+
+fn main() {
+    let credentials = Credentials {
+        user: "admin".to_owned(),
+        password: "hunter2".to_owned(),
+        token: vec![0; 16],
+        key: Secret("k".to_owned()),
+    };
+    assert_eq!(
+        format!("{:?}", credentials),
+        r#"Credentials { user: "admin", password: <redacted>, token: <redacted len=16>, .. }"#,
+    );
+
+    let session = Session(7, Secret("s".to_owned()));
+    assert_eq!(format!("{:?}", session), "Session(7, ..)");
+
+    let basic = Auth::Basic {
+        user: "admin".to_owned(),
+        password: "hunter2".to_owned(),
+    };
+    assert_eq!(
+        format!("{:?}", basic),
+        r#"Basic { user: "admin", password: <redacted> }"#,
+    );
+    assert_eq!(
+        format!("{:?}", Auth::Key(Secret("k".to_owned()))),
+        "Key(..)"
+    );
+}
@@ -0,0 +1,80 @@
+// Large collections and strings can be cut short. #[debug(max_items = N)]
+// prints the first N elements followed by a count of the rest, keeping the
+// list, set or map shape of the standard collections. Set on the struct, it is
+// the default for every field whose type is a standard collection.
+// #[debug(max_len = N)] keeps the first N characters of a string.
+
+use derive_debug::CustomDebug;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(CustomDebug)]
+#[debug(max_items = 3)]
+pub struct Batch<'a> {
+    ids: Vec<u32>,
+    tags: BTreeSet<&'static str>,
+    #[debug(max_items = 1)]
+    sizes: BTreeMap<String, usize>,
+    slice: &'a [u8],
+    #[debug(max_len = 5)]
+    name: String,
+    #[debug(max_len = 5)]
+    label: &'static str,
+}
+
+#[derive(CustomDebug)]
+pub struct Log {
+    #[debug(max_items = 2)]
+    lines: Vec<String>,
+}
+
+fn main() {
+    let batch = Batch {
+        ids: (1..=10).collect(),
+        tags: ["a", "b"].into_iter().collect(),
+        sizes: [
+            ("x".to_owned(), 1),
+            ("y".to_owned(), 2),
+            ("z".to_owned(), 3),
+        ]
+        .into_iter()
+        .collect(),
+        slice: &[1, 2, 3],
+        name: "Grüße, Jürgen".to_owned(),
+        label: "short",
+    };
+    assert_eq!(
+        format!("{:?}", batch),
+        r#"Batch { ids: [1, 2, 3, ... (7 more)], tags: {"a", "b"}, sizes: {"x": 1, ... (2 more)}, slice: [1, 2, 3], name: "Grüße"... (8 more), label: "short" }"#,
+    );
+
+    let expected = r#"Batch {
+    ids: [
+        1,
+        2,
+        3,
+        ... (7 more),
+    ],
+    tags: {
+        "a",
+        "b",
+    },
+    sizes: {
+        "x": 1,
+        ... (2 more),
+    },
+    slice: [
+        1,
+        2,
+        3,
+    ],
+    name: "Grüße"... (8 more),
+    label: "short",
+}"#;
+    assert_eq!(format!("{:#?}", batch), expected);
+
+    // Fields without the attribute are printed in full.
+    let log = Log {
+        lines: vec!["a".to_owned(), "b".to_owned()],
+    };
+    assert_eq!(format!("{:?}", log), r#"Log { lines: ["a", "b"] }"#);
+}
@@ -0,0 +1,59 @@
+// When a format string is not enough, #[debug(with = "path")] names a function
+// `fn(&T, &mut fmt::Formatter) -> fmt::Result` that formats the field. The
+// field's type does not need to implement Debug, and the function may be
+// generic or live in another module.
+
+use derive_debug::CustomDebug;
+use std::fmt;
+use std::time::Duration;
+
+pub struct Bytes(u64);
+
+fn human_size(bytes: &Bytes, f: &mut fmt::Formatter) -> fmt::Result {
+    match bytes.0 {
+        n if n >= 1 << 20 => write!(f, "{} MiB", n >> 20),
+        n if n >= 1 << 10 => write!(f, "{} KiB", n >> 10),
+        n => write!(f, "{} B", n),
+    }
+}
+
+mod fmt_util {
+    use std::fmt;
+    use std::time::Duration;
+
+    pub fn millis(duration: &Duration, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}ms", duration.as_millis())
+    }
+
+    pub fn hex<T: fmt::LowerHex>(value: &T, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", value)
+    }
+}
+
+#[derive(CustomDebug)]
+pub struct Transfer {
+    #[debug(with = "human_size")]
+    size: Bytes,
+    #[debug(with = "fmt_util::millis")]
+    elapsed: Duration,
+    #[debug(with = "fmt_util::hex")]
+    checksum: u32,
+}
+
+#[derive(CustomDebug)]
+pub enum Event {
+    Done(#[debug(with = "human_size")] Bytes),
+}
+
+fn main() {
+    let transfer = Transfer {
+        size: Bytes(3 << 20),
+        elapsed: Duration::from_millis(1500),
+        checksum: 0xbeef,
+    };
+    assert_eq!(
+        format!("{:?}", transfer),
+        "Transfer { size: 3 MiB, elapsed: 1500ms, checksum: 0xbeef }",
+    );
+    assert_eq!(format!("{:?}", Event::Done(Bytes(2048))), "Done(2 KiB)");
+}
@@ -0,0 +1,48 @@
+// Byte buffers have presets for the usual encodings, since std renders them as
+// long lists of decimal numbers: #[debug(hex)], #[debug(base64)], and
+// #[debug(hexdump)], which is hex in `{:?}` and a hexdump -C style table in
+// `{:#?}`. They work on anything that is AsRef<[u8]>.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Packet {
+    #[debug(hex)]
+    magic: [u8; 4],
+    #[debug(base64)]
+    key: Vec<u8>,
+    #[debug(hexdump)]
+    payload: Vec<u8>,
+}
+
+#[derive(CustomDebug)]
+pub struct Keys(
+    #[debug(base64)] &'static [u8],
+    #[debug(base64)] &'static str,
+);
+
+fn main() {
+    let packet = Packet {
+        magic: [0xca, 0xfe, 0xba, 0xbe],
+        key: b"Hello".to_vec(),
+        payload: b"Hello, world!\n\x00\xff and more".to_vec(),
+    };
+    assert_eq!(
+        format!("{:?}", packet),
+        r#"Packet { magic: 0xcafebabe, key: "SGVsbG8=", payload: 0x48656c6c6f2c20776f726c64210a00ff20616e64206d6f7265 }"#,
+    );
+
+    let expected = r#"Packet {
+    magic: 0xcafebabe,
+    key: "SGVsbG8=",
+    payload: [
+        00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|,
+        00000010  20 61 6e 64 20 6d 6f 72  65                       | and more|,
+    ],
+}"#;
+    assert_eq!(format!("{:#?}", packet), expected);
+
+    let keys = Keys(b"", "Man");
+    assert_eq!(format!("{:?}", keys), r#"Keys("", "TWFu")"#);
+    assert_eq!(format!("{:?}", Keys(b"Ma", "M")), r#"Keys("TWE=", "TQ==")"#);
+}
@@ -0,0 +1,75 @@
+// CustomDisplay implements Display from a #[display = "..."] format string on
+// the struct, or on each variant of an enum. Placeholders refer to fields by
+// name, or by index for tuple fields, and take the same format specs as
+// #[debug = "..."]. Unit variants without a format string display as their
+// name. As with CustomDebug, bounds are inferred for the type parameters that
+// are actually formatted, with the trait their placeholder uses.
+
+use derive_debug::{CustomDebug, CustomDisplay};
+use std::marker::PhantomData;
+
+#[derive(CustomDebug, CustomDisplay)]
+#[display = "{host}:{port}"]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+#[derive(CustomDebug, CustomDisplay)]
+pub enum Error {
+    #[display = "cannot connect to {0} (attempt {1}/{1:?})"]
+    Connect(Endpoint, u32),
+    #[display = "read {read} of {expected:#x} bytes"]
+    Short {
+        read: usize,
+        expected: usize,
+    },
+    Timeout,
+    #[debug(rename = "Eof")]
+    #[display = "unexpected end of input"]
+    UnexpectedEof,
+}
+
+#[derive(CustomDisplay)]
+#[display = "{value:>6} ({{}})"]
+pub struct Labelled<T, U> {
+    value: T,
+    // Never formatted, so U needs no bound.
+    label: PhantomData<U>,
+}
+
+#[derive(CustomDisplay)]
+#[display = "{} and {}"]
+pub struct Pair<T>(T, T);
+
+pub struct NotDisplay;
+
+fn main() {
+    let endpoint = Endpoint {
+        host: "localhost".to_owned(),
+        port: 8080,
+    };
+    assert_eq!(endpoint.to_string(), "localhost:8080");
+    assert_eq!(
+        Error::Connect(endpoint, 3).to_string(),
+        "cannot connect to localhost:8080 (attempt 3/3)",
+    );
+    assert_eq!(
+        Error::Short {
+            read: 10,
+            expected: 255,
+        }
+        .to_string(),
+        "read 10 of 0xff bytes",
+    );
+    assert_eq!(Error::Timeout.to_string(), "Timeout");
+    assert_eq!(Error::UnexpectedEof.to_string(), "unexpected end of input");
+    assert_eq!(format!("{:?}", Error::UnexpectedEof), "Eof");
+
+    let labelled = Labelled::<_, NotDisplay> {
+        value: 42,
+        label: PhantomData,
+    };
+    assert_eq!(labelled.to_string(), "    42 ({})");
+    assert_eq!(Pair(1, 2).to_string(), "1 and 2");
+}
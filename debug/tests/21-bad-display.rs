@@ -0,0 +1,19 @@
+// Placeholders naming a field that does not exist are reported on the format
+// string, pointing at the offending placeholder where the compiler supports it.
+
+use derive_debug::CustomDisplay;
+
+#[derive(CustomDisplay)]
+#[display = "{host}:{prot}"]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+#[derive(CustomDisplay)]
+pub enum Error {
+    #[display = "error {2}"]
+    Code(i32, String),
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+// HashMap and HashSet iterate in a random order, which makes their Debug output
+// unsuitable for snapshot tests. #[debug(sorted)] prints the entries of a field
+// sorted, by key for maps, and combines with max_items. Only the sorted items
+// (or keys) need to be Ord, and the bound is only added for that field.
+
+use derive_debug::CustomDebug;
+use std::collections::{HashMap, HashSet};
+
+#[derive(CustomDebug)]
+pub struct Index<K, V> {
+    #[debug(sorted)]
+    entries: HashMap<K, V>,
+    #[debug(sorted, max_items = 2)]
+    tags: HashSet<&'static str>,
+}
+
+// Debug but not Ord.
+#[derive(Debug)]
+pub struct Value(u32);
+
+fn main() {
+    let index = Index {
+        entries: (0..20).map(|i| (i, Value(i * 10))).collect(),
+        tags: ["c", "a", "d", "b"].into_iter().collect(),
+    };
+    let expected = format!(
+        "Index {{ entries: {{{}}}, tags: {{\"a\", \"b\", ... (2 more)}} }}",
+        (0..20)
+            .map(|i| format!("{}: Value({})", i, i * 10))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    assert_eq!(format!("{:?}", index), expected);
+
+    let expected = r#"Index {
+    entries: {
+        1: Value(
+            10,
+        ),
+        2: Value(
+            20,
+        ),
+    },
+    tags: {},
+}"#;
+    let index = Index {
+        entries: [(2, Value(20)), (1, Value(10))].into_iter().collect(),
+        tags: HashSet::new(),
+    };
+    assert_eq!(format!("{:#?}", index), expected);
+}
@@ -0,0 +1,76 @@
+// Graphs built from Rc<RefCell<..>> may contain cycles, which make the derived
+// Debug recurse until the stack overflows. #[debug(detect_cycles)] prints
+// `<cycle>` in place of a value that is already being formatted further up,
+// recognized by its address and type, and #[debug(depth_limit = N)] prints
+// `...` in place of values nested more than N deep. Both count only values of
+// the type carrying the attribute.
+
+use derive_debug::CustomDebug;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(CustomDebug)]
+#[debug(detect_cycles)]
+pub struct Node {
+    name: &'static str,
+    next: Option<Rc<RefCell<Node>>>,
+}
+
+#[derive(CustomDebug)]
+#[debug(detect_cycles)]
+pub struct Wrapper<T> {
+    inner: T,
+}
+
+#[derive(CustomDebug)]
+#[debug(depth_limit = 2)]
+pub enum List {
+    Cons(u32, Box<List>),
+    Nil,
+}
+
+fn main() {
+    let a = Rc::new(RefCell::new(Node {
+        name: "a",
+        next: None,
+    }));
+    let b = Rc::new(RefCell::new(Node {
+        name: "b",
+        next: Some(a.clone()),
+    }));
+    a.borrow_mut().next = Some(b.clone());
+    assert_eq!(
+        format!("{:?}", a.borrow()),
+        r#"Node { name: "a", next: Some(RefCell { value: Node { name: "b", next: Some(RefCell { value: <cycle> }) } }) }"#,
+    );
+
+    // The same value may appear twice without being a cycle.
+    let shared = Rc::new(RefCell::new(Node {
+        name: "leaf",
+        next: None,
+    }));
+    let pair = [shared.clone(), shared];
+    assert_eq!(
+        format!("{:?}", pair),
+        r#"[RefCell { value: Node { name: "leaf", next: None } }, RefCell { value: Node { name: "leaf", next: None } }]"#,
+    );
+
+    // A value at the same address as its parent, but of another type, is not a cycle.
+    let nested = Wrapper {
+        inner: Wrapper { inner: 1u8 },
+    };
+    assert_eq!(
+        format!("{:?}", nested),
+        "Wrapper { inner: Wrapper { inner: 1 } }"
+    );
+
+    let list = List::Cons(
+        1,
+        Box::new(List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))))),
+    );
+    assert_eq!(format!("{:?}", list), "Cons(1, Cons(2, ...))");
+    assert_eq!(format!("{:?}", List::Nil), "Nil");
+
+    // Break the cycle so the nodes are freed.
+    a.borrow_mut().next = None;
+}
@@ -0,0 +1,118 @@
+// Named fields can be printed under another name with #[debug(rename = "...")],
+// and #[debug(order = "alphabetical")] on the type prints them sorted by their
+// printed name ("declared", the default, keeps the declaration order).
+//
+// #[debug(flatten)] inlines the fields of a nested CustomDebug struct with
+// named fields into the parent's output, as if they were declared in its
+// place. Fields skipped in the nested struct still mark the output as
+// non-exhaustive. A flattened field of generic type is bounded by DebugFields
+// rather than Debug. Flatten can't be combined with order = "alphabetical",
+// see 27-flatten-order.rs.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Meta {
+    created: u64,
+    #[debug(rename = "by")]
+    created_by: &'static str,
+}
+
+#[derive(CustomDebug)]
+pub struct Secret {
+    kind: &'static str,
+    #[debug(skip)]
+    value: String,
+}
+
+#[derive(CustomDebug)]
+pub struct Document {
+    #[debug(rename = "id")]
+    document_id: u32,
+    #[debug(flatten)]
+    meta: Meta,
+    title: String,
+}
+
+#[derive(CustomDebug)]
+pub struct Tagged<T> {
+    tag: &'static str,
+    #[debug(flatten)]
+    inner: T,
+}
+
+#[derive(CustomDebug)]
+#[debug(order = "alphabetical")]
+pub struct Sorted<T> {
+    zeta: T,
+    #[debug(rename = "beta")]
+    omega: u8,
+    alpha: u8,
+}
+
+#[derive(CustomDebug)]
+pub enum Event {
+    Login {
+        user: &'static str,
+        #[debug(flatten)]
+        secret: Secret,
+    },
+}
+
+fn main() {
+    let doc = Document {
+        document_id: 7,
+        meta: Meta {
+            created: 1700000000,
+            created_by: "alice",
+        },
+        title: "Notes".to_owned(),
+    };
+    assert_eq!(
+        format!("{:?}", doc),
+        r#"Document { id: 7, created: 1700000000, by: "alice", title: "Notes" }"#,
+    );
+    assert_eq!(
+        format!("{:#?}", doc),
+        r#"Document {
+    id: 7,
+    created: 1700000000,
+    by: "alice",
+    title: "Notes",
+}"#,
+    );
+
+    let tagged = Tagged {
+        tag: "v1",
+        inner: Meta {
+            created: 0,
+            created_by: "bob",
+        },
+    };
+    assert_eq!(
+        format!("{:?}", tagged),
+        r#"Tagged { tag: "v1", created: 0, by: "bob" }"#,
+    );
+
+    let sorted = Sorted {
+        zeta: 'z',
+        omega: 2,
+        alpha: 1,
+    };
+    assert_eq!(
+        format!("{:?}", sorted),
+        "Sorted { alpha: 1, beta: 2, zeta: 'z' }",
+    );
+
+    let login = Event::Login {
+        user: "bob",
+        secret: Secret {
+            kind: "password",
+            value: "hunter2".to_owned(),
+        },
+    };
+    assert_eq!(
+        format!("{:?}", login),
+        r#"Login { user: "bob", kind: "password", .. }"#,
+    );
+}
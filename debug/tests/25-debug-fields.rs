@@ -0,0 +1,67 @@
+// Besides Debug, CustomDebug implements the DebugFields trait, which visits each
+// field as a name and a `&dyn Debug` value, following the same #[debug]
+// attributes. That is enough to emit key/value logs without a serializer per
+// type. Tuple fields are named by their index, enums visit the fields of the
+// current variant, and hand-written impls can be flattened like derived ones.
+
+use derive_debug::{CustomDebug, DebugFields};
+use std::fmt::Debug;
+
+#[derive(CustomDebug)]
+pub struct Request {
+    #[debug(rename = "http.method")]
+    method: &'static str,
+    #[debug(flatten)]
+    peer: Peer,
+    #[debug(redact)]
+    token: String,
+    #[debug(skip)]
+    body: Vec<u8>,
+}
+
+pub struct Peer {
+    addr: [u8; 4],
+}
+
+impl DebugFields for Peer {
+    fn debug_fields(&self, visit: &mut dyn FnMut(&str, &dyn Debug)) {
+        let [a, b, c, d] = self.addr;
+        visit("peer.addr", &format_args!("{}.{}.{}.{}", a, b, c, d));
+    }
+}
+
+#[derive(CustomDebug)]
+pub enum Event {
+    Started(u32, #[debug = "{:?}s"] u64),
+    Stopped,
+}
+
+fn to_json(value: &dyn DebugFields) -> String {
+    let mut entries = Vec::new();
+    value.debug_fields(&mut |name, value| {
+        entries.push(format!("{:?}: {:?}", name, format!("{:?}", value)));
+    });
+    format!("{{{}}}", entries.join(", "))
+}
+
+fn main() {
+    let request = Request {
+        method: "GET",
+        peer: Peer {
+            addr: [127, 0, 0, 1],
+        },
+        token: "secret".to_owned(),
+        body: Vec::new(),
+    };
+    assert_eq!(
+        to_json(&request),
+        r#"{"http.method": "\"GET\"", "peer.addr": "127.0.0.1", "token": "<redacted>"}"#,
+    );
+    assert_eq!(
+        format!("{:?}", request),
+        r#"Request { http.method: "GET", peer.addr: 127.0.0.1, token: <redacted>, .. }"#,
+    );
+
+    assert_eq!(to_json(&Event::Started(7, 30)), r#"{"0": "7", "1": "30s"}"#);
+    assert_eq!(to_json(&Event::Stopped), "{}");
+}
@@ -0,0 +1,115 @@
+// CustomDebug also implements DebugDiff, whose debug_diff lists the fields that
+// print differently between two values as (path, left, right), to find what
+// differs in large structs when an assertion fails. Fields are formatted as
+// configured by their #[debug] attributes, so redacted values are never
+// revealed. Fields whose type implements DebugDiff are compared field by field,
+// with their paths joined by `.`, also behind a reference, Box, Rc or Arc, and
+// flattened fields as if they were declared in the parent. Enums holding
+// different variants differ as a whole.
+
+use derive_debug::{CustomDebug, DebugDiff};
+use std::rc::Rc;
+
+#[derive(CustomDebug)]
+pub struct Server {
+    name: String,
+    #[debug(rename = "addr")]
+    address: Address,
+    #[debug(redact)]
+    password: String,
+    #[debug(skip)]
+    connections: u32,
+    #[debug(flatten)]
+    limits: Limits,
+    state: State,
+    tags: Vec<&'static str>,
+}
+
+#[derive(CustomDebug)]
+pub struct Address(#[debug = "{:?}"] [u8; 4], u16);
+
+#[derive(CustomDebug)]
+pub struct Limits {
+    #[debug = "{}s"]
+    timeout: u32,
+}
+
+#[derive(CustomDebug)]
+pub struct Pointers<'a> {
+    boxed: Box<Limits>,
+    shared: Rc<Limits>,
+    borrowed: &'a Limits,
+}
+
+#[derive(CustomDebug)]
+pub enum State {
+    Running { pid: u32 },
+    Stopped,
+}
+
+fn server() -> Server {
+    Server {
+        name: "web".to_owned(),
+        address: Address([10, 0, 0, 1], 80),
+        password: "hunter2".to_owned(),
+        connections: 12,
+        limits: Limits { timeout: 30 },
+        state: State::Running { pid: 100 },
+        tags: vec!["a"],
+    }
+}
+
+fn diff(path: &str, left: &str, right: &str) -> (String, String, String) {
+    (path.to_owned(), left.to_owned(), right.to_owned())
+}
+
+fn main() {
+    assert_eq!(server().debug_diff(&server()), []);
+
+    let left = server();
+    let mut right = server();
+    right.address.1 = 8080;
+    right.password = "swordfish".to_owned();
+    right.connections = 0;
+    right.limits.timeout = 60;
+    right.state = State::Running { pid: 200 };
+    right.tags.push("b");
+    assert_eq!(
+        left.debug_diff(&right),
+        [
+            diff("addr.1", "80", "8080"),
+            diff("timeout", "30s", "60s"),
+            diff("state.pid", "100", "200"),
+            diff("tags", r#"["a"]"#, r#"["a", "b"]"#),
+        ],
+    );
+
+    right = server();
+    right.state = State::Stopped;
+    assert_eq!(
+        left.debug_diff(&right),
+        [diff("state", "Running { pid: 100 }", "Stopped")],
+    );
+    assert_eq!(
+        State::Stopped.debug_diff(&State::Running { pid: 1 }),
+        [diff("", "Stopped", "Running { pid: 1 }")],
+    );
+
+    let limits = Limits { timeout: 30 };
+    let pointers = |timeout| Pointers {
+        boxed: Box::new(Limits { timeout }),
+        shared: Rc::new(Limits { timeout }),
+        borrowed: &limits,
+    };
+    let other = Limits { timeout: 60 };
+    let mut right = pointers(60);
+    right.borrowed = &other;
+    assert_eq!(
+        pointers(30).debug_diff(&right),
+        [
+            diff("boxed.timeout", "30s", "60s"),
+            diff("shared.timeout", "30s", "60s"),
+            diff("borrowed.timeout", "30s", "60s"),
+        ],
+    );
+}
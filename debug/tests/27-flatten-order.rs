@@ -0,0 +1,22 @@
+// The fields inlined by #[debug(flatten)] are only known at runtime, so they
+// cannot be sorted among the others: flatten is rejected on types printed with
+// #[debug(order = "alphabetical")].
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Meta {
+    b: u8,
+    y: u8,
+}
+
+#[derive(CustomDebug)]
+#[debug(order = "alphabetical")]
+pub struct Doc {
+    a: u8,
+    #[debug(flatten)]
+    zz: Meta,
+    c: u8,
+}
+
+fn main() {}
@@ -1,12 +1,31 @@
 #[test]
 fn tests() {
     let t = trybuild::TestCases::new();
-    //t.pass("tests/01-parse.rs");
-    //t.pass("tests/02-impl-debug.rs");
-    //t.pass("tests/03-custom-format.rs");
-    //t.pass("tests/04-type-parameter.rs");
-    //t.pass("tests/05-phantom-data.rs");
-    //t.pass("tests/06-bound-trouble.rs");
-    //t.pass("tests/07-associated-type.rs");
-    //t.pass("tests/08-escape-hatch.rs");
+    t.pass("tests/01-parse.rs");
+    t.pass("tests/02-impl-debug.rs");
+    t.pass("tests/03-custom-format.rs");
+    t.pass("tests/04-type-parameter.rs");
+    t.pass("tests/05-phantom-data.rs");
+    t.pass("tests/06-bound-trouble.rs");
+    t.pass("tests/07-associated-type.rs");
+    t.pass("tests/08-escape-hatch.rs");
+    t.pass("tests/09-pretty.rs");
+    t.compile_fail("tests/10-bad-format.rs");
+    t.pass("tests/11-bound-inference.rs");
+    t.pass("tests/12-field-bound.rs");
+    t.compile_fail("tests/13-bad-bound.rs");
+    t.pass("tests/14-enum.rs");
+    t.pass("tests/15-tuple-struct.rs");
+    t.pass("tests/16-skip-redact.rs");
+    t.pass("tests/17-truncate.rs");
+    t.pass("tests/18-with.rs");
+    t.pass("tests/19-bytes.rs");
+    t.pass("tests/20-display.rs");
+    t.compile_fail("tests/21-bad-display.rs");
+    t.pass("tests/22-sorted.rs");
+    t.pass("tests/23-cycles.rs");
+    t.pass("tests/24-rename-flatten.rs");
+    t.pass("tests/25-debug-fields.rs");
+    t.pass("tests/26-debug-diff.rs");
+    t.compile_fail("tests/27-flatten-order.rs");
 }